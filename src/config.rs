@@ -0,0 +1,362 @@
+// Configuration file (`--config`, TOML). It covers every command-line option plus tunables
+// that have no flag of their own, and lets individual tools be switched off. Values from the
+// command line win over environment variables, those over the file, and the file over the
+// built-in defaults; main.rs does the merging.
+
+use anyhow::{anyhow, Context, Result};
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::tools::{fetch_url_tool, jina_reader_tool, TOOL_NAMES};
+use crate::utils::address_guard::AllowRule;
+use crate::utils::body_limit::BodyKind;
+use crate::utils::domain_policy::DomainPattern;
+use crate::utils::http::{Backend, ProxySetting};
+use crate::utils::rate_limit::RateLimit;
+use crate::utils::retry::RetryPolicy;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Largest size in MiB whose byte count fits in a `u64`.
+pub const MAX_MIB: u64 = u64::MAX / MIB;
+
+/// Bytes in `mb` MiB. Saturates above `MAX_MIB`, which `validate` and the command-line
+/// parsers reject.
+pub fn mib_to_bytes(mb: u64) -> u64 {
+    mb.saturating_mul(MIB)
+}
+
+/// A value written as a string and parsed the same way as the matching command-line flag.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Parsed<T>(pub T);
+
+impl<'de, T> Deserialize<'de> for Parsed<T>
+where
+    T: FromStr<Err = anyhow::Error>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value
+            .parse()
+            .map(Parsed)
+            .map_err(|e| de::Error::custom(format!("{:#}", e)))
+    }
+}
+
+fn parsed_table<K, V>(table: &HashMap<Parsed<K>, Parsed<V>>) -> HashMap<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    table
+        .iter()
+        .map(|(key, value)| (key.0.clone(), value.0.clone()))
+        .collect()
+}
+
+/// Reads a secret kept in a file of its own. Surrounding whitespace, such as the trailing
+/// newline most editors add, is not part of the secret.
+pub fn read_secret(path: &Path) -> Result<String> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read secret file {}", path.display()))?;
+    let secret = text.trim();
+    if secret.is_empty() {
+        return Err(anyhow!("Secret file {} is empty", path.display()));
+    }
+    Ok(secret.to_string())
+}
+
+fn secret(value: &Option<String>, file: &Option<PathBuf>) -> Result<Option<String>> {
+    match (value, file) {
+        (Some(value), _) => Ok(Some(value.clone())),
+        (None, Some(path)) => read_secret(path).map(Some),
+        (None, None) => Ok(None),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub server: ServerSection,
+    pub http: HttpSection,
+    pub policy: PolicySection,
+    /// `BACKEND = "SECS[:BURST[:QUEUE]]"`
+    pub rate_limits: HashMap<Parsed<Backend>, Parsed<RateLimit>>,
+    /// `BACKEND = "ATTEMPTS[:BASE_SECS[:MAX_SECS]]"`
+    pub retry: HashMap<Parsed<Backend>, Parsed<RetryPolicy>>,
+    pub cache: CacheSection,
+    /// Body size caps in MiB by kind: html, text, json, pdf, other
+    pub body_limits: HashMap<Parsed<BodyKind>, u64>,
+    pub extraction: ExtractionSection,
+    pub google: GoogleSection,
+    pub jina: JinaSection,
+    /// Per-tool settings, keyed by tool name
+    pub tools: BTreeMap<String, ToolSection>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSection {
+    pub transport: Option<String>,
+    pub listen: Option<SocketAddr>,
+    pub max_concurrent_requests: Option<usize>,
+    pub quiet: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpSection {
+    /// Seconds
+    pub timeout: Option<u64>,
+    pub user_agent: Option<String>,
+    pub max_redirects: Option<usize>,
+    pub proxy: Option<Parsed<ProxySetting>>,
+    pub backend_proxies: HashMap<Parsed<Backend>, Parsed<ProxySetting>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicySection {
+    pub allow_private_hosts: Vec<Parsed<AllowRule>>,
+    pub allow_domains: Vec<Parsed<DomainPattern>>,
+    pub deny_domains: Vec<Parsed<DomainPattern>>,
+    pub respect_robots_txt: Option<bool>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheSection {
+    pub dir: Option<PathBuf>,
+    pub memory_mb: Option<u64>,
+    pub disk_mb: Option<u64>,
+    /// `BACKEND = SECS`
+    pub ttl: HashMap<Parsed<Backend>, u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExtractionSection {
+    pub text_width: Option<usize>,
+    pub min_dynamic_text_chars: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GoogleSection {
+    pub api_key: Option<String>,
+    /// File holding the API key, e.g. a mounted Docker or Kubernetes secret
+    pub api_key_file: Option<PathBuf>,
+    pub search_engine_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JinaSection {
+    pub api_key: Option<String>,
+    pub api_key_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolSection {
+    pub enabled: Option<bool>,
+    /// Default `maxLength`; fetch-url and jina-reader only
+    pub max_length: Option<usize>,
+}
+
+impl FileConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        let config: FileConfig =
+            toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Sections read only at startup that differ in `other`, e.g. `["cache"]`.
+    pub fn startup_changes(&self, other: &FileConfig) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.server != other.server {
+            changed.push("server");
+        }
+        if self.cache != other.cache {
+            changed.push("cache");
+        }
+        changed
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(transport) = &self.server.transport {
+            if !["stdio", "http", "sse"].contains(&transport.as_str()) {
+                return Err(anyhow!(
+                    "server.transport must be stdio, http or sse, got '{}'",
+                    transport
+                ));
+            }
+        }
+        if self.google.api_key.is_some() && self.google.api_key_file.is_some() {
+            return Err(anyhow!(
+                "Set either google.api_key or google.api_key_file, not both"
+            ));
+        }
+        if self.jina.api_key.is_some() && self.jina.api_key_file.is_some() {
+            return Err(anyhow!(
+                "Set either jina.api_key or jina.api_key_file, not both"
+            ));
+        }
+        if self.server.max_concurrent_requests == Some(0) {
+            return Err(anyhow!("server.max_concurrent_requests must be at least 1"));
+        }
+        if self.http.timeout == Some(0) {
+            return Err(anyhow!("http.timeout must be at least 1 second"));
+        }
+        let sizes = [
+            ("cache.memory_mb", self.cache.memory_mb),
+            ("cache.disk_mb", self.cache.disk_mb),
+        ];
+        let body_limits = self
+            .body_limits
+            .iter()
+            .map(|(kind, mb)| (kind.0.as_str(), Some(*mb)));
+        for (name, mb) in sizes.into_iter().chain(body_limits) {
+            if mb.is_some_and(|mb| mb > MAX_MIB) {
+                return Err(anyhow!("{} must be at most {} MiB", name, MAX_MIB));
+            }
+        }
+        if self.extraction.text_width == Some(0) {
+            return Err(anyhow!("extraction.text_width must be at least 1"));
+        }
+        for (name, tool) in &self.tools {
+            if !TOOL_NAMES.contains(&name.as_str()) {
+                return Err(anyhow!(
+                    "Unknown tool '{}' in [tools] (expected one of: {})",
+                    name,
+                    TOOL_NAMES.join(", ")
+                ));
+            }
+            let Some(max_length) = tool.max_length else {
+                continue;
+            };
+            let (min, max) = match name.as_str() {
+                "fetch-url" => fetch_url_tool::MAX_LENGTH_RANGE,
+                "jina-reader" => jina_reader_tool::MAX_LENGTH_RANGE,
+                _ => return Err(anyhow!("Tool '{}' has no max_length setting", name)),
+            };
+            if !(min..=max).contains(&max_length) {
+                return Err(anyhow!(
+                    "tools.{}.max_length must be between {} and {}",
+                    name,
+                    min,
+                    max
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn google_api_key(&self) -> Result<Option<String>> {
+        secret(&self.google.api_key, &self.google.api_key_file)
+    }
+
+    pub fn jina_api_key(&self) -> Result<Option<String>> {
+        secret(&self.jina.api_key, &self.jina.api_key_file)
+    }
+
+    /// Tools switched off with `enabled = false`.
+    pub fn disabled_tools(&self) -> impl Iterator<Item = &str> {
+        self.tools
+            .iter()
+            .filter(|(_, tool)| tool.enabled == Some(false))
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn rate_limits(&self) -> HashMap<Backend, RateLimit> {
+        parsed_table(&self.rate_limits)
+    }
+
+    pub fn retry_policies(&self) -> HashMap<Backend, RetryPolicy> {
+        parsed_table(&self.retry)
+    }
+
+    pub fn backend_proxies(&self) -> HashMap<Backend, ProxySetting> {
+        parsed_table(&self.http.backend_proxies)
+    }
+
+    pub fn cache_ttls(&self) -> HashMap<Backend, Duration> {
+        self.cache
+            .ttl
+            .iter()
+            .map(|(backend, secs)| (backend.0, Duration::from_secs(*secs)))
+            .collect()
+    }
+
+    /// Body size caps in bytes
+    pub fn body_limits(&self) -> HashMap<BodyKind, u64> {
+        self.body_limits
+            .iter()
+            .map(|(kind, mb)| (kind.0, mib_to_bytes(*mb)))
+            .collect()
+    }
+
+    pub fn max_length(&self, tool: &str) -> Option<usize> {
+        self.tools.get(tool).and_then(|tool| tool.max_length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sections_with_flag_syntax() {
+        let config: FileConfig = toml::from_str(
+            r#"
+            [server]
+            transport = "http"
+
+            [policy]
+            deny_domains = ["example.com"]
+
+            [rate_limits]
+            google = "1:5"
+
+            [cache.ttl]
+            fetch = 0
+
+            [body_limits]
+            pdf = 50
+
+            [tools.fetch-url]
+            max_length = 60000
+
+            [tools.felo-search]
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.server.transport.as_deref(), Some("http"));
+        assert_eq!(config.rate_limits()[&Backend::Google].burst, 5);
+        assert!(config.cache_ttls()[&Backend::Fetch].is_zero());
+        assert_eq!(config.max_length("fetch-url"), Some(60000));
+        assert_eq!(config.disabled_tools().collect::<Vec<_>>(), ["felo-search"]);
+
+        let typo = toml::from_str::<FileConfig>("[tools.fetch-url]\nenable = false\n");
+        assert!(typo.is_err());
+        let bad_limit = toml::from_str::<FileConfig>("[rate_limits]\ngoogle = \"fast\"\n");
+        assert!(bad_limit.is_err());
+        let huge: FileConfig =
+            toml::from_str("[body_limits]\npdf = 9223372036854775807\n").unwrap();
+        assert!(huge.validate().is_err());
+        let no_slots: FileConfig =
+            toml::from_str("[server]\nmax_concurrent_requests = 0\n").unwrap();
+        assert!(no_slots.validate().is_err());
+    }
+}
@@ -1,13 +1,36 @@
-use clap::{Arg, Command};
+use anyhow::Context;
+use clap::parser::ValueSource;
+use clap::{Arg, ArgMatches, Command};
+use std::collections::HashSet;
 use std::env;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::{error, info, warn};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
 
+mod config;
 mod mcp;
 mod tools;
 mod utils;
 
-use mcp::server::{GoogleSearchConfig, McpServer};
+use config::FileConfig;
+use mcp::server::{GoogleSearchConfig, McpServer, ToolSettings, DEFAULT_MAX_CONCURRENT_REQUESTS};
+use mcp::transport::StdioTransport;
+use utils::address_guard::AllowRule;
+use utils::cache::CacheConfig;
+use utils::domain_policy::{DomainPattern, DomainPolicy};
+use utils::http::{Backend, HttpConfig, ProxySetting};
+use utils::rate_limit::RateLimit;
+use utils::readability_extract::ExtractionSettings;
+use utils::redact::Redacting;
+use utils::retry::RetryPolicy;
+
+// How often the configuration file is checked for changes
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
 /// Prints a formatted box with the given lines
 /// Empty strings create empty lines, other strings are centered within the box
@@ -70,6 +93,371 @@ fn strip_ansi_codes(text: &str) -> String {
     result
 }
 
+/// Parses a `--rate-limit` value of the form `backend=SECS[:BURST[:QUEUE]]`
+fn parse_rate_limit(value: &str) -> Result<(Backend, RateLimit), String> {
+    let (backend, limit) = value
+        .split_once('=')
+        .ok_or_else(|| "expected BACKEND=SECS[:BURST[:QUEUE]], e.g. google=1:5".to_string())?;
+    let backend = backend.parse::<Backend>().map_err(|e| e.to_string())?;
+    let limit = limit.parse::<RateLimit>().map_err(|e| e.to_string())?;
+    Ok((backend, limit))
+}
+
+/// Parses a `--retry` value of the form `backend=ATTEMPTS[:BASE_SECS[:MAX_SECS]]`
+fn parse_retry(value: &str) -> Result<(Backend, RetryPolicy), String> {
+    let (backend, policy) = value.split_once('=').ok_or_else(|| {
+        "expected BACKEND=ATTEMPTS[:BASE_SECS[:MAX_SECS]], e.g. fetch=4:1".to_string()
+    })?;
+    let backend = backend.parse::<Backend>().map_err(|e| e.to_string())?;
+    let policy = policy.parse::<RetryPolicy>().map_err(|e| e.to_string())?;
+    Ok((backend, policy))
+}
+
+/// Parses a `--cache-ttl` value of the form `backend=SECS`
+fn parse_cache_ttl(value: &str) -> Result<(Backend, Duration), String> {
+    let (backend, secs) = value
+        .split_once('=')
+        .ok_or_else(|| "expected BACKEND=SECS, e.g. duckduckgo=86400".to_string())?;
+    let backend = backend.parse::<Backend>().map_err(|e| e.to_string())?;
+    let secs = secs
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("Invalid TTL '{}' (expected whole seconds)", secs))?;
+    Ok((backend, Duration::from_secs(secs)))
+}
+
+/// Parses a `--backend-proxy` value of the form `backend=url`
+fn parse_backend_proxy(value: &str) -> Result<(Backend, ProxySetting), String> {
+    let (backend, proxy) = value.split_once('=').ok_or_else(|| {
+        "expected BACKEND=URL, e.g. duckduckgo=socks5h://127.0.0.1:1080".to_string()
+    })?;
+    let backend = backend.parse::<Backend>().map_err(|e| e.to_string())?;
+    let proxy = proxy
+        .parse::<ProxySetting>()
+        .map_err(|e| format!("{:#}", e))?;
+    Ok((backend, proxy))
+}
+
+/// Whether `id` was given on the command line rather than taken from its default
+fn from_cli(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+/// Reads `name` from the environment, or else the file named by `<name>_FILE` (Docker and
+/// Kubernetes secrets).
+fn env_secret(name: &str) -> anyhow::Result<Option<String>> {
+    if let Ok(value) = env::var(name) {
+        return Ok(Some(value));
+    }
+    match env::var_os(format!("{}_FILE", name)) {
+        Some(path) => config::read_secret(Path::new(&path))
+            .map(Some)
+            .with_context(|| format!("{}_FILE", name)),
+        None => Ok(None),
+    }
+}
+
+/// Whether a proxy is set in the environment, which takes precedence over the file
+fn proxy_in_env() -> bool {
+    ["HTTPS_PROXY", "HTTP_PROXY", "ALL_PROXY"]
+        .into_iter()
+        .any(|name| env::var_os(name).is_some() || env::var_os(name.to_lowercase()).is_some())
+}
+
+/// Applies the settings that can change while the server runs: credentials, tool enablement,
+/// HTTP client, policies and limits. Called at startup and again on every reload. Nothing is
+/// applied unless all of it is valid, so a failed reload leaves the previous settings intact.
+fn apply_config(matches: &ArgMatches, file: &FileConfig) -> anyhow::Result<ToolSettings> {
+    // Get Google Search configuration from environment variables or command line arguments
+    let google_api_key = match matches.get_one::<String>("google-api-key") {
+        Some(key) => Some(key.clone()),
+        None => match env_secret("GOOGLE_API_KEY")? {
+            Some(key) => Some(key),
+            None => file.google_api_key()?,
+        },
+    };
+
+    let google_search_engine_id = matches
+        .get_one::<String>("google-search-engine-id")
+        .cloned()
+        .or_else(|| env::var("GOOGLE_SEARCH_ENGINE_ID").ok())
+        .or_else(|| file.google.search_engine_id.clone());
+
+    // Get Jina API key from command line or environment
+    let jina_api_key = match matches.get_one::<String>("jina-api-key") {
+        Some(key) => Some(key.clone()),
+        None => match env_secret("JINA_API_KEY")? {
+            Some(key) => Some(key),
+            None => file.jina_api_key()?,
+        },
+    };
+
+    let secrets: Vec<String> = google_api_key
+        .iter()
+        .chain(&jina_api_key)
+        .cloned()
+        .collect();
+
+    // Log Google Search configuration status (without exposing secrets)
+    match (&google_api_key, &google_search_engine_id) {
+        (Some(_), Some(_)) => {
+            info!("Google Search tool enabled");
+        }
+        (Some(_), None) => {
+            warn!("Google Search API key found but Search Engine ID missing - Google Search tool will be disabled");
+        }
+        (None, Some(_)) => {
+            warn!("Google Search Engine ID found but API key missing - Google Search tool will be disabled");
+        }
+        (None, None) => {
+            // Both are None, no action needed
+        }
+    }
+
+    // Log Jina Reader configuration status (without exposing secrets)
+    match &jina_api_key {
+        Some(_) => {
+            info!("Jina Reader tool enabled");
+        }
+        None => {
+            info!("Jina Reader API key not found - Jina Reader tool will be disabled");
+        }
+    }
+
+    let google_config =
+        if let (Some(api_key), Some(engine_id)) = (google_api_key, google_search_engine_id) {
+            Some(GoogleSearchConfig {
+                api_key,
+                search_engine_id: engine_id,
+            })
+        } else {
+            None
+        };
+
+    // Configure the HTTP client shared by all backends
+    let mut http_config = HttpConfig::default();
+    if let Some(secs) = matches
+        .get_one::<u64>("http-timeout")
+        .copied()
+        .or(file.http.timeout)
+    {
+        http_config.timeout = Duration::from_secs(secs);
+    }
+    if let Some(user_agent) = matches
+        .get_one::<String>("user-agent")
+        .or(file.http.user_agent.as_ref())
+    {
+        http_config.user_agent = user_agent.clone();
+    }
+    if let Some(max_redirects) = matches
+        .get_one::<usize>("max-redirects")
+        .copied()
+        .or(file.http.max_redirects)
+    {
+        http_config.max_redirects = max_redirects;
+    }
+    http_config.proxy = matches
+        .get_one::<ProxySetting>("proxy")
+        .cloned()
+        .or_else(|| {
+            file.http
+                .proxy
+                .as_ref()
+                .filter(|_| !proxy_in_env())
+                .map(|proxy| proxy.0.clone())
+        });
+    http_config.backend_proxies = file.backend_proxies();
+    if let Some(overrides) = matches.get_many::<(Backend, ProxySetting)>("backend-proxy") {
+        http_config.backend_proxies.extend(overrides.cloned());
+    }
+    http_config.address_policy.allow = match matches.get_many::<AllowRule>("allow-private-host") {
+        Some(rules) => rules.cloned().collect(),
+        None => file
+            .policy
+            .allow_private_hosts
+            .iter()
+            .map(|rule| rule.0.clone())
+            .collect(),
+    };
+    if !http_config.address_policy.allow.is_empty() {
+        warn!(
+            "Requests to internal addresses allowed for: {}",
+            http_config
+                .address_policy
+                .allow
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    if let Some(proxy) = &http_config.proxy {
+        info!("Using proxy {} for outbound requests", proxy);
+    }
+    for (backend, proxy) in &http_config.backend_proxies {
+        info!("Using proxy {} for {} requests", proxy, backend);
+    }
+
+    // Sites agents may read, for fetch tools and search results alike
+    let patterns = |id: &str, from_file: &[config::Parsed<DomainPattern>]| match matches
+        .get_many::<DomainPattern>(
+        id,
+    ) {
+        Some(patterns) => patterns.cloned().collect(),
+        None => from_file.iter().map(|pattern| pattern.0.clone()).collect(),
+    };
+    let domain_policy = DomainPolicy {
+        allow: patterns("allow-domain", &file.policy.allow_domains),
+        deny: patterns("deny-domain", &file.policy.deny_domains),
+    };
+    if !domain_policy.is_empty() {
+        info!(
+            "Domain policy: {} allowed, {} denied patterns",
+            domain_policy.allow.len(),
+            domain_policy.deny.len()
+        );
+    }
+
+    let mut rate_limits = file.rate_limits();
+    if let Some(limits) = matches.get_many::<(Backend, RateLimit)>("rate-limit") {
+        rate_limits.extend(limits.cloned());
+    }
+    let mut retry_policies = file.retry_policies();
+    if let Some(policies) = matches.get_many::<(Backend, RetryPolicy)>("retry") {
+        retry_policies.extend(policies.cloned());
+    }
+
+    let respect_robots_txt =
+        matches.get_flag("respect-robots-txt") || file.policy.respect_robots_txt == Some(true);
+    if respect_robots_txt {
+        info!("robots.txt compliance enabled for page fetches");
+    }
+
+    let disabled_tools: HashSet<String> = file.disabled_tools().map(str::to_string).collect();
+    if !disabled_tools.is_empty() {
+        let mut names: Vec<_> = disabled_tools.iter().map(String::as_str).collect();
+        names.sort_unstable();
+        info!("Tools disabled in configuration: {}", names.join(", "));
+    }
+
+    // Building the HTTP clients is the last step that can fail, so it goes first
+    utils::http::configure(&http_config).context("Invalid HTTP client configuration")?;
+    // Keep the keys out of the logs, e.g. in a request dump or an error quoting a URL
+    utils::redact::set_secrets(secrets);
+    utils::domain_policy::configure(domain_policy);
+    utils::rate_limit::configure(rate_limits);
+    utils::retry::configure(retry_policies);
+    utils::robots::set_enabled(respect_robots_txt);
+    utils::body_limit::configure(file.body_limits());
+    let defaults = ExtractionSettings::default();
+    utils::readability_extract::configure(ExtractionSettings {
+        text_width: file.extraction.text_width.unwrap_or(defaults.text_width),
+        min_dynamic_text_chars: file
+            .extraction
+            .min_dynamic_text_chars
+            .unwrap_or(defaults.min_dynamic_text_chars),
+    });
+    tools::fetch_url_tool::set_default_max_length(
+        file.max_length("fetch-url")
+            .unwrap_or(tools::fetch_url_tool::BUILTIN_MAX_LENGTH),
+    );
+    tools::jina_reader_tool::set_default_max_length(
+        file.max_length("jina-reader")
+            .unwrap_or(tools::jina_reader_tool::BUILTIN_MAX_LENGTH),
+    );
+
+    Ok(ToolSettings {
+        google_config,
+        jina_api_key,
+        disabled_tools,
+    })
+}
+
+/// Reloads the configuration file when it changes (checked every `poll_interval`) or on SIGHUP,
+/// and hands `server` the tool settings `apply` makes of it. An invalid file is reported and
+/// the previous configuration stays in effect. `startup` is the file as read at startup.
+async fn watch_config<F>(
+    path: PathBuf,
+    poll_interval: Duration,
+    startup: FileConfig,
+    server: Arc<McpServer>,
+    apply: F,
+) where
+    F: Fn(&FileConfig) -> anyhow::Result<ToolSettings>,
+{
+    let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last_modified = modified(&path);
+    let mut interval = tokio::time::interval(poll_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut hangup = hangup_signal();
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let current = modified(&path);
+                if current == last_modified {
+                    continue;
+                }
+                last_modified = current;
+                info!("{} changed; reloading configuration", path.display());
+            }
+            _ = hangup.recv() => {
+                info!("SIGHUP received; reloading configuration");
+            }
+        }
+
+        let applied = FileConfig::load(&path).and_then(|file| {
+            let tools = apply(&file)?;
+            for section in startup.startup_changes(&file) {
+                warn!("[{}] changed; restart the server to apply it", section);
+            }
+            Ok(tools)
+        });
+        match applied {
+            Ok(tools) => {
+                server.reconfigure(tools);
+                info!("Reloaded configuration from {}", path.display());
+            }
+            Err(e) => error!("Keeping the previous configuration: {:#}", e),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn hangup_signal() -> HangupSignal {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::hangup()) {
+        Ok(signal) => HangupSignal(Some(signal)),
+        Err(e) => {
+            warn!("Cannot listen for SIGHUP: {}", e);
+            HangupSignal(None)
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn hangup_signal() -> HangupSignal {
+    HangupSignal
+}
+
+#[cfg(unix)]
+struct HangupSignal(Option<tokio::signal::unix::Signal>);
+
+#[cfg(not(unix))]
+struct HangupSignal;
+
+impl HangupSignal {
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = &mut self.0 {
+            if signal.recv().await.is_some() {
+                return;
+            }
+        }
+        std::future::pending::<()>().await
+    }
+}
+
 #[tokio::main]
 async fn main() {
     // Parse command line arguments first
@@ -86,6 +474,14 @@ async fn main() {
             - url-fetch: Fetch web pages and convert them to markdown\n\
             - felo-search: Search using Felo AI for AI-generated responses",
         )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .help("TOML configuration file; command-line options and environment variables take precedence over it")
+                .value_parser(clap::value_parser!(PathBuf))
+                .action(clap::ArgAction::Set),
+        )
         .arg(
             Arg::new("google-api-key")
                 .long("google-api-key")
@@ -107,6 +503,151 @@ async fn main() {
                 .help("Jina Reader API key")
                 .action(clap::ArgAction::Set),
         )
+        .arg(
+            Arg::new("max-concurrent-requests")
+                .long("max-concurrent-requests")
+                .value_name("N")
+                .help("Maximum number of tool calls executed concurrently (default: 8)")
+                .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("transport")
+                .long("transport")
+                .value_name("TRANSPORT")
+                .help("Transport to serve MCP over: stdio (default), http (Streamable HTTP) or sse (legacy HTTP+SSE)")
+                .value_parser(["stdio", "http", "sse"])
+                .default_value("stdio")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("listen")
+                .long("listen")
+                .value_name("ADDR")
+                .help("Address for the http and sse transports to listen on")
+                .value_parser(clap::value_parser!(SocketAddr))
+                .default_value("127.0.0.1:8080")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("http-timeout")
+                .long("http-timeout")
+                .value_name("SECS")
+                .help("Timeout in seconds for outbound HTTP requests, including the body (default: 30)")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("user-agent")
+                .long("user-agent")
+                .value_name("UA")
+                .help("User-Agent for outbound HTTP requests (search backends may still rotate their own)")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("max-redirects")
+                .long("max-redirects")
+                .value_name("N")
+                .help("Maximum number of HTTP redirects to follow (default: 10)")
+                .value_parser(clap::value_parser!(usize))
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("proxy")
+                .long("proxy")
+                .value_name("URL")
+                .help("Proxy for all outbound requests (http://, https://, socks5://, socks5h://, or 'direct'); defaults to HTTPS_PROXY/HTTP_PROXY/ALL_PROXY, NO_PROXY is honored")
+                .value_parser(clap::value_parser!(ProxySetting))
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("backend-proxy")
+                .long("backend-proxy")
+                .value_name("BACKEND=URL")
+                .help("Proxy override for one backend (duckduckgo, google, felo, jina, fetch); repeatable")
+                .value_parser(parse_backend_proxy)
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("allow-private-host")
+                .long("allow-private-host")
+                .value_name("HOST|IP|CIDR")
+                .help("Allow requests to this internal host, address or network (loopback, private and link-local targets are refused by default); repeatable")
+                .value_parser(clap::value_parser!(AllowRule))
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("allow-domain")
+                .long("allow-domain")
+                .value_name("PATTERN")
+                .help("Only fetch and show search results from matching sites (example.com includes subdomains, * globs); repeatable or comma-separated")
+                .value_parser(clap::value_parser!(DomainPattern))
+                .value_delimiter(',')
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("deny-domain")
+                .long("deny-domain")
+                .value_name("PATTERN")
+                .help("Never fetch or show search results from matching sites; takes precedence over --allow-domain; repeatable or comma-separated")
+                .value_parser(clap::value_parser!(DomainPattern))
+                .value_delimiter(',')
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("respect-robots-txt")
+                .long("respect-robots-txt")
+                .help("Refuse page fetches disallowed by the site's robots.txt and honor Crawl-delay")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("rate-limit")
+                .long("rate-limit")
+                .value_name("BACKEND=SECS[:BURST[:QUEUE]]")
+                .help("Rate limit for one backend (duckduckgo, google, felo, jina, or fetch per host): minimum seconds between requests, burst size and max queued requests; repeatable")
+                .value_parser(parse_rate_limit)
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("retry")
+                .long("retry")
+                .value_name("BACKEND=ATTEMPTS[:BASE_SECS[:MAX_SECS]]")
+                .help("Retry policy for one backend: total attempts for transient failures (1 disables retries), first backoff and maximum backoff in seconds; repeatable")
+                .value_parser(parse_retry)
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("cache-dir")
+                .long("cache-dir")
+                .value_name("DIR")
+                .help("Keep cached search results and pages in this directory so they survive restarts")
+                .value_parser(clap::value_parser!(PathBuf))
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("cache-memory-mb")
+                .long("cache-memory-mb")
+                .value_name("MB")
+                .help("Memory for cached entries in MiB, least recently used evicted first (default: 64)")
+                .value_parser(clap::value_parser!(u64).range(..=config::MAX_MIB))
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("cache-disk-mb")
+                .long("cache-disk-mb")
+                .value_name("MB")
+                .help("Disk space for cached entries in MiB when --cache-dir is set (default: 512)")
+                .value_parser(clap::value_parser!(u64).range(..=config::MAX_MIB))
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("cache-ttl")
+                .long("cache-ttl")
+                .value_name("BACKEND=SECS")
+                .help("How long cached results of one backend stay valid; 0 disables caching for it; repeatable")
+                .value_parser(parse_cache_ttl)
+                .action(clap::ArgAction::Append),
+        )
         .arg(
             Arg::new("quiet")
                 .long("quiet")
@@ -116,81 +657,100 @@ async fn main() {
         )
         .get_matches();
 
-    // Initialize tracing to stderr only (stdout is reserved for JSON-RPC)
-    let log_level = if std::env::var("RUST_LOG").is_ok() {
-        // Use RUST_LOG if set
-        None
-    } else if matches.get_flag("quiet") {
-        // In quiet mode, only show errors
-        Some("error")
-    } else {
-        // Default to info level
-        Some("info")
+    // Settings missing from the command line and environment are taken from the file
+    let file = match matches.get_one::<PathBuf>("config") {
+        Some(path) => match FileConfig::load(path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Invalid configuration: {:#}", e);
+                process::exit(1);
+            }
+        },
+        None => FileConfig::default(),
     };
+    let quiet = matches.get_flag("quiet") || file.server.quiet.unwrap_or(false);
 
-    let subscriber = tracing_subscriber::fmt().with_writer(std::io::stderr);
-
-    if let Some(level) = log_level {
-        std::env::set_var("RUST_LOG", level);
-    }
-
-    subscriber.init();
+    // Initialize tracing to stderr only (stdout is reserved for JSON-RPC); events are also
+    // forwarded to MCP clients that asked for them with logging/setLevel
+    let stderr_level = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|level| level.parse::<LevelFilter>().ok())
+        .unwrap_or(if quiet {
+            // In quiet mode, only show errors
+            LevelFilter::ERROR
+        } else {
+            // Default to info level
+            LevelFilter::INFO
+        });
 
-    // Get Google Search configuration from environment variables or command line arguments
-    let google_api_key = matches
-        .get_one::<String>("google-api-key")
-        .cloned()
-        .or_else(|| env::var("GOOGLE_API_KEY").ok());
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(Redacting(std::io::stderr))
+                .with_filter(stderr_level),
+        )
+        .with(mcp::logging::layer())
+        .init();
 
-    let google_search_engine_id = matches
-        .get_one::<String>("google-search-engine-id")
-        .cloned()
-        .or_else(|| env::var("GOOGLE_SEARCH_ENGINE_ID").ok());
+    let tools = match apply_config(&matches, &file) {
+        Ok(tools) => tools,
+        Err(e) => {
+            error!("{:#}", e);
+            process::exit(1);
+        }
+    };
 
-    // Get Jina API key from command line or environment
-    let jina_api_key = matches
-        .get_one::<String>("jina-api-key")
-        .cloned()
-        .or_else(|| std::env::var("JINA_API_KEY").ok());
+    let max_concurrent_requests = matches
+        .get_one::<usize>("max-concurrent-requests")
+        .copied()
+        .or_else(|| {
+            let value = env::var("MCP_MAX_CONCURRENT_REQUESTS").ok()?;
+            match value.trim().parse::<usize>() {
+                Ok(n) if n >= 1 => Some(n),
+                _ => {
+                    error!(
+                        "Invalid MCP_MAX_CONCURRENT_REQUESTS '{}' (expected a number of at least 1)",
+                        value
+                    );
+                    process::exit(1);
+                }
+            }
+        })
+        .or(file.server.max_concurrent_requests)
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_REQUESTS);
 
-    // Log Google Search configuration status (without exposing secrets)
-    match (&google_api_key, &google_search_engine_id) {
-        (Some(_), Some(_)) => {
-            info!("Google Search tool enabled");
-        }
-        (Some(_), None) => {
-            warn!("Google Search API key found but Search Engine ID missing - Google Search tool will be disabled");
-        }
-        (None, Some(_)) => {
-            warn!("Google Search Engine ID found but API key missing - Google Search tool will be disabled");
-        }
-        (None, None) => {
-            // Both are None, no action needed
-        }
+    let mut cache_config = CacheConfig {
+        dir: matches
+            .get_one::<PathBuf>("cache-dir")
+            .or(file.cache.dir.as_ref())
+            .cloned(),
+        ttls: file.cache_ttls(),
+        ..CacheConfig::default()
+    };
+    if let Some(mb) = matches
+        .get_one::<u64>("cache-memory-mb")
+        .copied()
+        .or(file.cache.memory_mb)
+    {
+        cache_config.memory_bytes = config::mib_to_bytes(mb);
     }
-
-    // Log Jina Reader configuration status (without exposing secrets)
-    match &jina_api_key {
-        Some(_) => {
-            info!("Jina Reader tool enabled");
-        }
-        None => {
-            info!("Jina Reader API key not found - Jina Reader tool will be disabled");
-        }
+    if let Some(mb) = matches
+        .get_one::<u64>("cache-disk-mb")
+        .copied()
+        .or(file.cache.disk_mb)
+    {
+        cache_config.disk_bytes = config::mib_to_bytes(mb);
+    }
+    if let Some(ttls) = matches.get_many::<(Backend, Duration)>("cache-ttl") {
+        cache_config.ttls.extend(ttls.cloned());
+    }
+    if let Err(e) = utils::cache::configure(cache_config) {
+        error!("Invalid cache configuration: {:#}", e);
+        process::exit(1);
     }
-
-    let google_config =
-        if let (Some(api_key), Some(engine_id)) = (google_api_key, google_search_engine_id) {
-            Some(GoogleSearchConfig {
-                api_key,
-                search_engine_id: engine_id,
-            })
-        } else {
-            None
-        };
 
     // Display promotional message (unless quiet mode)
-    if !matches.get_flag("quiet") {
+    if !quiet {
         print_box(&[
             "",
             "\x1b[1m\x1b[31m MCP-GetWeb: Web Search Server \x1b[0m",
@@ -205,9 +765,99 @@ async fn main() {
     // Start the MCP server
     info!("Starting MCP server...");
 
-    let mut server = McpServer::new(google_config, jina_api_key);
-    if let Err(e) = server.start().await {
+    let server = Arc::new(McpServer::new(tools, max_concurrent_requests));
+    let addr = match file.server.listen {
+        Some(listen) if !from_cli(&matches, "listen") => listen,
+        _ => *matches
+            .get_one::<SocketAddr>("listen")
+            .expect("listen has a default value"),
+    };
+    let transport = match &file.server.transport {
+        Some(transport) if !from_cli(&matches, "transport") => Some(transport),
+        _ => matches.get_one::<String>("transport"),
+    }
+    .cloned();
+    if let Some(path) = matches.get_one::<PathBuf>("config") {
+        let matches = matches.clone();
+        tokio::spawn(watch_config(
+            path.clone(),
+            CONFIG_POLL_INTERVAL,
+            file,
+            server.clone(),
+            move |file| apply_config(&matches, file),
+        ));
+    }
+    let result = match transport.as_deref() {
+        Some("http") => mcp::streamable_http::serve(server, addr).await,
+        Some("sse") => mcp::sse::serve(server, addr).await,
+        _ => StdioTransport::new().serve(server).await,
+    };
+    if let Err(e) = result {
         error!("Failed to start server: {}", e);
         process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp::dispatch::{McpHandler, Session};
+    use mcp::transport::MessageSender;
+    use mcp::types::{McpMessage, McpNotification, McpRequest};
+
+    #[tokio::test]
+    async fn reloads_the_file_and_announces_tool_changes() {
+        let path = env::temp_dir().join(format!("getweb-reload-test-{}.toml", process::id()));
+        std::fs::write(&path, "").unwrap();
+        let server = Arc::new(McpServer::new(ToolSettings::default(), 1));
+
+        // An initialized session that listens for notifications
+        let session = Arc::new(Session::new());
+        let (sender, mut rx) = MessageSender::channel();
+        let initialize = McpMessage::Request(McpRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(1)),
+            method: "initialize".to_string(),
+            params: Some(serde_json::json!({
+                "protocolVersion": "2025-06-18",
+                "capabilities": {},
+                "clientInfo": { "name": "test", "version": "1" }
+            })),
+        });
+        Arc::clone(&server).handle_message(&session, initialize, sender.clone());
+        rx.recv().await.unwrap();
+        let initialized = McpMessage::Notification(McpNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/initialized".to_string(),
+            params: None,
+        });
+        Arc::clone(&server).handle_message(&session, initialized, sender.clone());
+        session.set_notifier(Some(sender));
+
+        // Applies only the tool enablement, leaving the global settings alone
+        let watcher = tokio::spawn(watch_config(
+            path.clone(),
+            Duration::from_millis(20),
+            FileConfig::default(),
+            Arc::clone(&server),
+            |file| {
+                Ok(ToolSettings {
+                    disabled_tools: file.disabled_tools().map(str::to_string).collect(),
+                    ..ToolSettings::default()
+                })
+            },
+        ));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        std::fs::write(&path, "[tools.felo-search]\nenabled = false\n").unwrap();
+
+        let notification = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("tools/list_changed within 5 seconds")
+            .unwrap();
+        let notification: serde_json::Value = serde_json::from_str(&notification).unwrap();
+        assert_eq!(notification["method"], "notifications/tools/list_changed");
+
+        watcher.abort();
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::task::AbortHandle;
+use tokio_util::task::TaskTracker;
+
+use super::protocol::ProtocolVersion;
+use super::transport::MessageSender;
+use super::types::{LoggingLevel, McpMessage, McpNotification};
+
+/// Transport-independent message dispatch.
+/// Transports decode JSON-RPC messages and hand them over together with the session
+/// they belong to and a sender through which replies for that message are delivered.
+pub trait McpHandler: Send + Sync + 'static {
+    /// Handles one message. Requests are processed in background tasks tracked by
+    /// `session`, so this returns immediately; the response is sent through `sender`.
+    fn handle_message(
+        self: Arc<Self>,
+        session: &Arc<Session>,
+        message: McpMessage,
+        sender: MessageSender,
+    );
+}
+
+/// Protocol state of a single client connection.
+/// Stdio has exactly one session; HTTP transports create one per client session.
+pub struct Session {
+    initialized: AtomicBool,
+    protocol_version: Mutex<ProtocolVersion>,
+    // Minimum level forwarded as notifications/message; nothing is sent until the client sets one
+    log_level: Mutex<Option<LoggingLevel>>,
+    // In-flight request tasks keyed by the JSON text of their JSON-RPC id
+    in_flight: Mutex<HashMap<String, AbortHandle>>,
+    tasks: TaskTracker,
+    // Channel for notifications not tied to a request; set by the transport when it has one
+    notifier: Mutex<Option<MessageSender>>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            initialized: AtomicBool::new(false),
+            protocol_version: Mutex::new(ProtocolVersion::default()),
+            log_level: Mutex::new(None),
+            in_flight: Mutex::new(HashMap::new()),
+            tasks: TaskTracker::new(),
+            notifier: Mutex::new(None),
+        }
+    }
+
+    pub fn set_initialized(&self) {
+        self.initialized.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::SeqCst)
+    }
+
+    /// Sets where server-initiated notifications go (the stdio output, the SSE stream or the
+    /// Streamable HTTP GET stream); `None` while there is no such channel.
+    pub fn set_notifier(&self, sender: Option<MessageSender>) {
+        *self.notifier.lock().unwrap() = sender;
+    }
+
+    /// Whether a notification channel is set and still has a listener.
+    pub fn has_notifier(&self) -> bool {
+        self.notifier
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|sender| !sender.is_closed())
+    }
+
+    /// Sends a notification that is not part of a request's response. Returns false if the
+    /// session has no channel for it.
+    pub fn notify(&self, notification: McpNotification) -> bool {
+        match self.notifier.lock().unwrap().as_ref() {
+            Some(sender) => sender.send_notification(notification).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Protocol revision agreed on during `initialize`.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        *self.protocol_version.lock().unwrap()
+    }
+
+    pub fn set_protocol_version(&self, version: ProtocolVersion) {
+        *self.protocol_version.lock().unwrap() = version;
+    }
+
+    pub fn log_level(&self) -> Option<LoggingLevel> {
+        *self.log_level.lock().unwrap()
+    }
+
+    pub fn set_log_level(&self, level: LoggingLevel) {
+        *self.log_level.lock().unwrap() = Some(level);
+    }
+
+    /// Runs the handling of request `id` in its own task, registered so it can be cancelled.
+    pub fn spawn_request<F>(self: &Arc<Self>, id: Option<&serde_json::Value>, fut: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let key = id.map(request_key);
+        let session = Arc::clone(self);
+        let task_key = key.clone();
+
+        // Hold the registry lock across spawn so a fast task cannot
+        // deregister itself before it has been registered
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let handle = self.tasks.spawn(async move {
+            fut.await;
+            if let Some(key) = task_key {
+                // A request reusing the id while this one ran has its own entry by now
+                let mut in_flight = session.in_flight.lock().unwrap();
+                if in_flight
+                    .get(&key)
+                    .is_some_and(|handle| handle.id() == tokio::task::id())
+                {
+                    in_flight.remove(&key);
+                }
+            }
+        });
+        if let Some(key) = key {
+            in_flight.insert(key, handle.abort_handle());
+        }
+    }
+
+    /// Aborts the task handling request `id`. Returns false if it already finished or is unknown.
+    pub fn cancel_request(&self, id: &serde_json::Value) -> bool {
+        match self.in_flight.lock().unwrap().remove(&request_key(id)) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Aborts every in-flight request of this session (used when the session is terminated).
+    pub fn close(&self) {
+        for (_, handle) in self.in_flight.lock().unwrap().drain() {
+            handle.abort();
+        }
+        self.tasks.close();
+        self.set_notifier(None);
+    }
+
+    /// Waits until all spawned request tasks have finished.
+    pub async fn wait_idle(&self) {
+        self.tasks.close();
+        self.tasks.wait().await;
+    }
+}
+
+fn request_key(id: &serde_json::Value) -> String {
+    id.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Sets its flag when the task holding it is dropped, i.e. aborted or finished
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_request_aborts_its_task() {
+        let session = Arc::new(Session::new());
+        let dropped = Arc::new(AtomicBool::new(false));
+        let guard = DropFlag(Arc::clone(&dropped));
+        session.spawn_request(Some(&json!(7)), async move {
+            let _guard = guard;
+            std::future::pending::<()>().await;
+        });
+        session.spawn_request(Some(&json!("done")), async {});
+        tokio::task::yield_now().await;
+
+        assert!(!session.cancel_request(&json!("done")));
+        assert!(!session.cancel_request(&json!("7")));
+        assert!(session.cancel_request(&json!(7)));
+        assert!(!session.cancel_request(&json!(7)));
+
+        session.wait_idle().await;
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn a_reused_id_stays_cancellable_after_the_first_request_ends() {
+        let session = Arc::new(Session::new());
+        let (finish, finished) = tokio::sync::oneshot::channel::<()>();
+        session.spawn_request(Some(&json!(1)), async move {
+            let _ = finished.await;
+        });
+        session.spawn_request(Some(&json!(1)), std::future::pending());
+
+        finish.send(()).unwrap();
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert!(session.cancel_request(&json!(1)));
+        session.wait_idle().await;
+    }
+
+    #[tokio::test]
+    async fn closing_a_session_aborts_all_requests() {
+        let session = Arc::new(Session::new());
+        let (sender, _rx) = MessageSender::channel();
+        session.set_notifier(Some(sender));
+        for id in 0..3 {
+            session.spawn_request(Some(&json!(id)), std::future::pending());
+        }
+
+        session.close();
+        session.wait_idle().await;
+        assert!(!session.has_notifier());
+        assert!(!session.cancel_request(&json!(0)));
+    }
+}
@@ -0,0 +1,202 @@
+// Forwards `tracing` events to MCP clients as `notifications/message`.
+// Request tasks run inside `with_log_target`, so an event reaches the client whose request
+// produced it, filtered by the level that client selected with `logging/setLevel`.
+
+use std::cell::Cell;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use super::dispatch::Session;
+use super::transport::MessageSender;
+use super::types::{LoggingLevel, LoggingMessageParams, McpNotification};
+use crate::utils::redact::redact;
+
+// Only this crate's events are forwarded; dependency internals (hyper, h2, ...) stay on stderr
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+// Modules logging under an explicit `target:` rather than their module path
+const CUSTOM_TARGETS: &[&str] = &["readability_extract"];
+// Wire-level message dumps would echo every response back as a log notification
+const TRANSPORT_TARGET: &str = concat!(env!("CARGO_CRATE_NAME"), "::mcp::transport");
+
+struct LogTarget {
+    session: Arc<Session>,
+    sender: MessageSender,
+}
+
+tokio::task_local! {
+    static LOG_TARGET: LogTarget;
+}
+
+thread_local! {
+    // Set while an event is being forwarded, so logging inside the send path cannot recurse
+    static FORWARDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `fut` with its log events forwarded to the client behind `session`/`sender`.
+pub async fn with_log_target<F: Future>(
+    session: Arc<Session>,
+    sender: MessageSender,
+    fut: F,
+) -> F::Output {
+    LOG_TARGET.scope(LogTarget { session, sender }, fut).await
+}
+
+/// Tracing layer delivering this crate's events to the MCP client of the current request.
+/// The target filter keeps debug callsites of dependencies disabled.
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let targets = CUSTOM_TARGETS.iter().fold(
+        Targets::new().with_target(CRATE_TARGET, LevelFilter::DEBUG),
+        |targets, target| targets.with_target(*target, LevelFilter::DEBUG),
+    );
+    McpLogLayer.with_filter(targets)
+}
+
+struct McpLogLayer;
+
+impl<S: Subscriber> Layer<S> for McpLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let target = metadata.target();
+        if target.starts_with(TRANSPORT_TARGET) {
+            return;
+        }
+
+        let level = mcp_level(*metadata.level());
+        let _ = LOG_TARGET.try_with(|log_target| {
+            match log_target.session.log_level() {
+                Some(min_level) if level >= min_level => {}
+                _ => return,
+            }
+            if FORWARDING.with(|forwarding| forwarding.replace(true)) {
+                return;
+            }
+
+            let mut visitor = JsonVisitor::default();
+            event.record(&mut visitor);
+            let params = LoggingMessageParams {
+                level,
+                logger: target.rsplit("::").next().map(|name| name.to_string()),
+                data: visitor.into_data(),
+            };
+            let notification = McpNotification {
+                jsonrpc: "2.0".to_string(),
+                method: "notifications/message".to_string(),
+                params: Some(serde_json::to_value(params).unwrap()),
+            };
+            // The client may already be gone; there is nobody left to tell
+            let _ = log_target.sender.send_notification(notification);
+
+            FORWARDING.with(|forwarding| forwarding.set(false));
+        });
+    }
+}
+
+fn mcp_level(level: Level) -> LoggingLevel {
+    match level {
+        Level::ERROR => LoggingLevel::Error,
+        Level::WARN => LoggingLevel::Warning,
+        Level::INFO => LoggingLevel::Info,
+        Level::DEBUG | Level::TRACE => LoggingLevel::Debug,
+    }
+}
+
+// Collects the event message and any structured fields
+#[derive(Default)]
+struct JsonVisitor {
+    message: Option<String>,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl JsonVisitor {
+    // A bare message becomes a string; with extra fields an object is sent instead
+    fn into_data(self) -> serde_json::Value {
+        let message = self.message.unwrap_or_default();
+        if self.fields.is_empty() {
+            return serde_json::Value::String(message);
+        }
+        let mut data = self.fields;
+        data.insert("message".to_string(), serde_json::Value::String(message));
+        serde_json::Value::Object(data)
+    }
+
+    fn insert(&mut self, field: &Field, value: serde_json::Value) {
+        self.fields.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for JsonVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        let value = redact(value).into_owned();
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.insert(field, value.into());
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = redact(&format!("{:?}", value)).into_owned();
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.insert(field, value.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+
+    #[tokio::test]
+    async fn forwards_events_at_or_above_the_selected_level() {
+        let _subscriber =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(layer()));
+        let session = Arc::new(Session::new());
+        let (sender, mut rx) = MessageSender::channel();
+
+        with_log_target(Arc::clone(&session), sender, async {
+            tracing::warn!("before logging/setLevel");
+            session.set_log_level(LoggingLevel::Warning);
+            tracing::info!("below the level");
+            tracing::warn!(status = 503u64, "slow response");
+            tracing::error!(target: "hyper::proto", "dependency internals");
+        })
+        .await;
+
+        let mut messages = Vec::new();
+        while let Ok(line) = rx.try_recv() {
+            messages.push(serde_json::from_str::<serde_json::Value>(&line).unwrap());
+        }
+        assert_eq!(messages.len(), 1);
+        let params = &messages[0]["params"];
+        assert_eq!(params["level"], "warning");
+        assert_eq!(params["logger"], "tests");
+        assert_eq!(
+            params["data"],
+            serde_json::json!({ "message": "slow response", "status": 503 })
+        );
+    }
+}
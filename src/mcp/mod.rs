@@ -1,3 +1,9 @@
+pub mod dispatch;
+pub mod logging;
+pub mod prompts;
+pub mod protocol;
 pub mod server;
+pub mod sse;
+pub mod streamable_http;
 pub mod transport;
 pub mod types;
@@ -0,0 +1,252 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+use super::types::{ContentItem, GetPromptResult, Prompt, PromptArgument, PromptMessage};
+
+const DEFAULT_MAX_SOURCES: usize = 3;
+
+/// Research workflows offered through `prompts/list` and `prompts/get`.
+pub static PROMPT_DEFINITIONS: Lazy<Vec<Prompt>> = Lazy::new(|| {
+    vec![
+        Prompt {
+            name: "research-topic".to_string(),
+            title: Some("Research a Topic".to_string()),
+            description: Some(
+                "Search the web, read the most relevant results and answer with citations"
+                    .to_string(),
+            ),
+            arguments: vec![
+                PromptArgument {
+                    name: "topic".to_string(),
+                    description: Some("The topic or question to research".to_string()),
+                    required: Some(true),
+                },
+                PromptArgument {
+                    name: "maxSources".to_string(),
+                    description: Some(format!(
+                        "Number of search results to read (default: {})",
+                        DEFAULT_MAX_SOURCES
+                    )),
+                    required: Some(false),
+                },
+            ],
+        },
+        Prompt {
+            name: "summarize-url".to_string(),
+            title: Some("Read and Summarize URL".to_string()),
+            description: Some("Fetch a web page or PDF and summarize it".to_string()),
+            arguments: vec![
+                PromptArgument {
+                    name: "url".to_string(),
+                    description: Some("The URL to summarize".to_string()),
+                    required: Some(true),
+                },
+                PromptArgument {
+                    name: "focus".to_string(),
+                    description: Some("Aspect the summary should concentrate on".to_string()),
+                    required: Some(false),
+                },
+            ],
+        },
+        Prompt {
+            name: "compare-sources".to_string(),
+            title: Some("Compare Sources".to_string()),
+            description: Some(
+                "Read several URLs and compare what they say, noting agreements and conflicts"
+                    .to_string(),
+            ),
+            arguments: vec![
+                PromptArgument {
+                    name: "urls".to_string(),
+                    description: Some(
+                        "URLs to compare, separated by commas or newlines".to_string(),
+                    ),
+                    required: Some(true),
+                },
+                PromptArgument {
+                    name: "question".to_string(),
+                    description: Some("Question the comparison should answer".to_string()),
+                    required: Some(false),
+                },
+            ],
+        },
+    ]
+});
+
+/// Tools the prompts may point the model at, depending on server configuration.
+pub struct PromptTools {
+    pub google_search: bool,
+    pub jina_reader: bool,
+}
+
+/// Renders prompt `name` with the client-supplied arguments.
+/// Errors describe an unknown prompt or a missing/invalid argument.
+pub fn get_prompt(
+    name: &str,
+    arguments: &HashMap<String, String>,
+    tools: &PromptTools,
+) -> Result<GetPromptResult, String> {
+    let prompt = PROMPT_DEFINITIONS
+        .iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| format!("Unknown prompt: {}", name))?;
+
+    for argument in &prompt.arguments {
+        let provided = matches!(arguments.get(&argument.name), Some(v) if !v.trim().is_empty());
+        if argument.required == Some(true) && !provided {
+            return Err(format!("Missing required argument: {}", argument.name));
+        }
+    }
+
+    let text = match name {
+        "research-topic" => {
+            let max_sources = match arguments.get("maxSources") {
+                Some(value) => value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| (1..=10).contains(n))
+                    .ok_or_else(|| "maxSources must be an integer from 1 to 10".to_string())?,
+                None => DEFAULT_MAX_SOURCES,
+            };
+            research_topic(arguments["topic"].trim(), max_sources, tools)
+        }
+        "summarize-url" => summarize_url(
+            arguments["url"].trim(),
+            arguments.get("focus").map(|f| f.trim()),
+            tools,
+        ),
+        "compare-sources" => {
+            let urls: Vec<&str> = arguments["urls"]
+                .split([',', '\n'])
+                .map(str::trim)
+                .filter(|u| !u.is_empty())
+                .collect();
+            if urls.len() < 2 {
+                return Err("compare-sources needs at least two URLs".to_string());
+            }
+            compare_sources(&urls, arguments.get("question").map(|q| q.trim()))
+        }
+        _ => unreachable!("prompt definitions and renderers are out of sync"),
+    };
+
+    Ok(GetPromptResult {
+        description: prompt.description.clone(),
+        messages: vec![PromptMessage {
+            role: "user".to_string(),
+            content: ContentItem::text(text),
+        }],
+    })
+}
+
+fn search_tools(tools: &PromptTools) -> &'static str {
+    if tools.google_search {
+        "`google-search` (or `duckduckgo-search` if it returns nothing useful)"
+    } else {
+        "`duckduckgo-search`"
+    }
+}
+
+fn reader_tools(tools: &PromptTools) -> &'static str {
+    if tools.jina_reader {
+        "`url-fetch` (use `jina-reader` for pages that come back empty or garbled)"
+    } else {
+        "`url-fetch` (use `fetch-url` for pages that come back empty or garbled)"
+    }
+}
+
+fn research_topic(topic: &str, max_sources: usize, tools: &PromptTools) -> String {
+    format!(
+        "Research the following topic: {topic}\n\n\
+         1. Search the web with {search}. Try a second query with different wording if the first results are thin.\n\
+         2. Pick the {max_sources} most relevant and trustworthy results and read each of them with {reader}.\n\
+         3. Write an answer based only on what you read. Cite sources inline as [1], [2], ... and end with a \
+         numbered list of the URLs you cited. Say so explicitly when the sources disagree or do not cover part of the question.",
+        topic = topic,
+        search = search_tools(tools),
+        max_sources = max_sources,
+        reader = reader_tools(tools),
+    )
+}
+
+fn summarize_url(url: &str, focus: Option<&str>, tools: &PromptTools) -> String {
+    let focus = match focus {
+        Some(focus) if !focus.is_empty() => format!(" Concentrate on: {}.", focus),
+        _ => String::new(),
+    };
+    format!(
+        "Read {url} with {reader} and summarize it.{focus}\n\n\
+         Start with a two-sentence overview, then list the key points as bullets. \
+         Quote figures and names exactly as written, and mention if the page could not be read completely.",
+        url = url,
+        reader = reader_tools(tools),
+        focus = focus,
+    )
+}
+
+fn compare_sources(urls: &[&str], question: Option<&str>) -> String {
+    let list = urls
+        .iter()
+        .enumerate()
+        .map(|(i, url)| format!("[{}] {}", i + 1, url))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let goal = match question {
+        Some(question) if !question.is_empty() => {
+            format!("with respect to this question: {}", question)
+        }
+        _ => "on their main claims".to_string(),
+    };
+    format!(
+        "Compare the following sources {goal}\n\n{list}\n\n\
+         Read each source with `url-fetch`. Then describe where they agree, where they conflict, \
+         and what only one of them covers, citing sources as [n]. Finish with your assessment of \
+         which source is most reliable and why.",
+        goal = goal,
+        list = list,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(name: &str, arguments: &[(&str, &str)]) -> Result<String, String> {
+        let arguments = arguments
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let tools = PromptTools {
+            google_search: true,
+            jina_reader: false,
+        };
+        get_prompt(name, &arguments, &tools).map(|result| match &result.messages[0].content {
+            ContentItem::Text { text } => text.clone(),
+            other => panic!("unexpected content {:?}", other),
+        })
+    }
+
+    #[test]
+    fn validates_arguments() {
+        assert_eq!(
+            render("research-topic", &[("topic", "  ")]).unwrap_err(),
+            "Missing required argument: topic"
+        );
+        assert!(render("research-topic", &[("topic", "rust"), ("maxSources", "11")]).is_err());
+        assert!(render("compare-sources", &[("urls", "https://a.example,\n")]).is_err());
+        assert_eq!(
+            render("no-such-prompt", &[]).unwrap_err(),
+            "Unknown prompt: no-such-prompt"
+        );
+
+        let text = render("research-topic", &[("topic", "rust"), ("maxSources", "5")]).unwrap();
+        assert!(text.contains("Pick the 5 most relevant"));
+        assert!(text.contains("`google-search`"));
+        let text = render(
+            "compare-sources",
+            &[("urls", "https://a.example, https://b.example")],
+        )
+        .unwrap();
+        assert!(text.contains("[2] https://b.example"));
+    }
+}
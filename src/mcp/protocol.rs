@@ -0,0 +1,194 @@
+use super::types::{CallToolResult, ContentItem, ToolDefinition};
+
+/// MCP protocol revisions this server can speak, oldest first.
+/// Until `initialize` has been handled a session is assumed to speak the oldest one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProtocolVersion {
+    #[default]
+    V2024_11_05,
+    V2025_03_26,
+    V2025_06_18,
+}
+
+impl ProtocolVersion {
+    pub const SUPPORTED: [ProtocolVersion; 3] = [
+        ProtocolVersion::V2024_11_05,
+        ProtocolVersion::V2025_03_26,
+        ProtocolVersion::V2025_06_18,
+    ];
+
+    pub const LATEST: ProtocolVersion = ProtocolVersion::V2025_06_18;
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProtocolVersion::V2024_11_05 => "2024-11-05",
+            ProtocolVersion::V2025_03_26 => "2025-03-26",
+            ProtocolVersion::V2025_06_18 => "2025-06-18",
+        }
+    }
+
+    pub fn parse(version: &str) -> Option<Self> {
+        Self::SUPPORTED.into_iter().find(|v| v.as_str() == version)
+    }
+
+    /// Picks the revision to answer an `initialize` request with.
+    /// An exact match wins; an unknown revision gets the newest one we support that
+    /// is not newer than it (revisions are dates, so they compare as strings), and a
+    /// client older than everything we support is offered the latest revision.
+    pub fn negotiate(requested: &str) -> Self {
+        if let Some(version) = Self::parse(requested) {
+            return version;
+        }
+        Self::SUPPORTED
+            .into_iter()
+            .rev()
+            .find(|v| v.as_str() <= requested)
+            .unwrap_or(Self::LATEST)
+    }
+
+    /// Tool annotations (readOnlyHint, ...) and audio content were added in 2025-03-26.
+    pub fn supports_tool_annotations(self) -> bool {
+        self >= ProtocolVersion::V2025_03_26
+    }
+
+    pub fn supports_audio_content(self) -> bool {
+        self >= ProtocolVersion::V2025_03_26
+    }
+
+    /// `title` fields, `outputSchema`/`structuredContent` and resource links arrived in 2025-06-18.
+    pub fn supports_titles(self) -> bool {
+        self >= ProtocolVersion::V2025_06_18
+    }
+
+    pub fn supports_structured_content(self) -> bool {
+        self >= ProtocolVersion::V2025_06_18
+    }
+
+    pub fn supports_resource_links(self) -> bool {
+        self >= ProtocolVersion::V2025_06_18
+    }
+
+    /// Removes the fields of a tool definition the negotiated revision does not know about.
+    pub fn tool_definition(self, mut tool: ToolDefinition) -> ToolDefinition {
+        if !self.supports_titles() {
+            tool.title = None;
+        }
+        if !self.supports_structured_content() {
+            tool.output_schema = None;
+        }
+        if !self.supports_tool_annotations() {
+            tool.annotations = None;
+        }
+        tool
+    }
+
+    /// Rewrites a tool result for the negotiated revision: structured content is dropped
+    /// (the text content always carries the same data) and content types the client
+    /// cannot understand are turned into text.
+    pub fn call_tool_result(self, mut result: CallToolResult) -> CallToolResult {
+        if !self.supports_structured_content() {
+            result.structured_content = None;
+        }
+
+        let texts: Vec<String> = result
+            .content
+            .iter()
+            .filter_map(|item| match item {
+                ContentItem::Text { text } => Some(text.clone()),
+                _ => None,
+            })
+            .collect();
+
+        result.content = result
+            .content
+            .into_iter()
+            .filter_map(|item| match item {
+                ContentItem::Audio { mime_type, .. } if !self.supports_audio_content() => {
+                    Some(ContentItem::text(format!(
+                        "[{} audio omitted: not supported by protocol {}]",
+                        mime_type,
+                        self.as_str()
+                    )))
+                }
+                ContentItem::ResourceLink { uri, name, .. } if !self.supports_resource_links() => {
+                    // Links already listed in the text output would only be duplicated
+                    if texts.iter().any(|text| text.contains(&uri)) {
+                        None
+                    } else {
+                        Some(ContentItem::text(format!("[{}]({})", name, uri)))
+                    }
+                }
+                item => Some(item),
+            })
+            .collect();
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiates_the_closest_supported_revision() {
+        assert_eq!(
+            ProtocolVersion::negotiate("2025-03-26"),
+            ProtocolVersion::V2025_03_26
+        );
+        // Unknown revisions between and after ours get the newest one not newer than them
+        assert_eq!(
+            ProtocolVersion::negotiate("2025-05-01"),
+            ProtocolVersion::V2025_03_26
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate("2026-01-01"),
+            ProtocolVersion::LATEST
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate("2024-01-01"),
+            ProtocolVersion::LATEST
+        );
+        assert_eq!(ProtocolVersion::parse("2025-05-01"), None);
+    }
+
+    #[test]
+    fn downgrades_tool_results_for_the_oldest_revision() {
+        let result = CallToolResult {
+            content: vec![
+                ContentItem::text("1. https://example.com/listed"),
+                ContentItem::Audio {
+                    data: "AAAA".to_string(),
+                    mime_type: "audio/wav".to_string(),
+                },
+                ContentItem::resource_link("https://example.com/listed", "Listed", None),
+                ContentItem::resource_link("https://example.com/other", "Other", None),
+            ],
+            ..CallToolResult::success("")
+        }
+        .with_structured_content(serde_json::json!({ "results": [] }));
+
+        let latest = ProtocolVersion::LATEST.call_tool_result(result.clone());
+        assert!(latest.structured_content.is_some());
+        assert_eq!(latest.content.len(), 4);
+
+        let old = ProtocolVersion::V2024_11_05.call_tool_result(result);
+        assert!(old.structured_content.is_none());
+        let texts: Vec<String> = old
+            .content
+            .into_iter()
+            .map(|item| match item {
+                ContentItem::Text { text } => text,
+                other => panic!("unexpected content {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "1. https://example.com/listed",
+                "[audio/wav audio omitted: not supported by protocol 2024-11-05]",
+                "[Other](https://example.com/other)",
+            ]
+        );
+    }
+}
@@ -1,72 +1,177 @@
-use anyhow::Result;
-use tracing::{debug, info, warn};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use tokio::sync::Semaphore;
+use tracing::{debug, error, info, warn};
 
-use super::transport::StdioTransport;
+use super::dispatch::{McpHandler, Session};
+use super::logging::with_log_target;
+use super::prompts::{self, PromptTools, PROMPT_DEFINITIONS};
+use super::protocol::ProtocolVersion;
+use super::transport::MessageSender;
 use super::types::*;
 use crate::tools::{
     duckduckgo_search_tool::{DuckDuckGoSearchTool, DUCKDUCKGO_SEARCH_TOOL_DEFINITION},
     felo_tool::{FeloTool, FELO_TOOL_DEFINITION},
-    fetch_url_tool::{FetchUrlTool, FETCH_URL_TOOL_DEFINITION},
+    fetch_url_tool::{fetch_url_tool_definition, FetchUrlTool},
     google_search_tool::{GoogleSearchTool, GOOGLE_SEARCH_TOOL_DEFINITION},
-    jina_reader_tool::{JinaReaderTool, JINA_READER_TOOL_DEFINITION},
+    jina_reader_tool::{jina_reader_tool_definition, JinaReaderTool},
     metadata_tool::{MetadataTool, METADATA_TOOL_DEFINITION},
     url_fetch_tool::{UrlFetchTool, URL_FETCH_TOOL_DEFINITION},
 };
+use crate::utils::cache;
+use crate::utils::domain_policy;
+use crate::utils::page_store::{self, RESOURCE_TEMPLATE};
+use crate::utils::progress::{with_progress, ProgressSink};
+use crate::utils::retry;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct GoogleSearchConfig {
     pub api_key: String,
     pub search_engine_id: String,
 }
 
+/// Default number of `tools/call` requests executed at the same time
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 8;
+
+#[derive(Debug, Deserialize)]
+struct CancelledParams {
+    #[serde(rename = "requestId")]
+    request_id: serde_json::Value,
+    reason: Option<String>,
+}
+
+/// Which tools the server offers and the credentials they use. Replaced as a whole when
+/// the configuration is reloaded.
+#[derive(Debug, Clone, Default)]
+pub struct ToolSettings {
+    pub google_config: Option<GoogleSearchConfig>,
+    pub jina_api_key: Option<String>,
+    /// Tools switched off in the configuration; neither listed nor callable
+    pub disabled_tools: HashSet<String>,
+}
+
 pub struct McpServer {
-    transport: StdioTransport,
-    google_config: Option<GoogleSearchConfig>,
-    jina_api_key: Option<String>,
-    initialized: bool,
+    tools: RwLock<ToolSettings>,
+    // Sessions that completed `initialize`, told about tool list changes
+    sessions: Mutex<Vec<Weak<Session>>>,
+    // Limits concurrently executing tool calls; other methods (ping, tools/list) are never queued
+    tool_call_slots: Semaphore,
+}
+
+impl McpHandler for McpServer {
+    fn handle_message(
+        self: Arc<Self>,
+        session: &Arc<Session>,
+        message: McpMessage,
+        sender: MessageSender,
+    ) {
+        match message {
+            McpMessage::Request(request) => {
+                let is_initialize = request.method == "initialize";
+                let task_session = Arc::clone(session);
+                let id = request.id.clone();
+                let log_sender = sender.clone();
+                session.spawn_request(
+                    id.as_ref(),
+                    with_log_target(Arc::clone(session), log_sender, async move {
+                        let response = self.handle_request(&task_session, request, &sender).await;
+                        if is_initialize && response.error.is_none() {
+                            self.register_session(&task_session);
+                        }
+                        if let Err(e) = sender.send_response(response) {
+                            error!("Failed to write response: {}", e);
+                        }
+                    }),
+                );
+            }
+            McpMessage::Notification(notification) => {
+                self.handle_notification(session, notification);
+            }
+        }
+    }
 }
 
 impl McpServer {
-    pub fn new(google_config: Option<GoogleSearchConfig>, jina_api_key: Option<String>) -> Self {
+    /// Runs up to `max_concurrent_requests` tool calls at a time; must be at least 1.
+    pub fn new(tools: ToolSettings, max_concurrent_requests: usize) -> Self {
         Self {
-            transport: StdioTransport::new(),
-            initialized: false,
-            google_config,
-            jina_api_key,
+            tools: RwLock::new(tools),
+            sessions: Mutex::new(Vec::new()),
+            tool_call_slots: Semaphore::new(max_concurrent_requests),
         }
     }
 
-    pub async fn start(&mut self) -> Result<()> {
-        info!("MCP server started and listening on stdio");
+    fn tool_settings(&self) -> ToolSettings {
+        self.tools.read().unwrap().clone()
+    }
 
-        loop {
-            match self.transport.read_message().await? {
-                Some(message) => match message {
-                    McpMessage::Request(request) => {
-                        let response = self.handle_request(request).await;
-                        self.transport.write_response(response).await?;
-                    }
-                    McpMessage::Notification(notification) => {
-                        self.handle_notification(notification).await;
-                    }
-                },
-                None => {
-                    info!("Client disconnected");
-                    break;
-                }
-            }
+    /// Applies reloaded tool settings. Clients are sent `notifications/tools/list_changed`
+    /// if the listed tools differ afterwards.
+    pub fn reconfigure(&self, tools: ToolSettings) {
+        let before = self.listed_tools();
+        *self.tools.write().unwrap() = tools;
+        let after = self.listed_tools();
+        // Compare the JSON forms, which include the configurable schema defaults
+        if serde_json::to_value(&before).ok() == serde_json::to_value(&after).ok() {
+            return;
+        }
+        info!(
+            "Tool list changed: {}",
+            after
+                .iter()
+                .map(|tool| tool.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        self.notify_sessions("notifications/tools/list_changed");
+    }
+
+    // Adds `session` to the ones notified of changes, unless it is there already
+    fn register_session(&self, session: &Arc<Session>) {
+        let session = Arc::downgrade(session);
+        let mut sessions = self.sessions.lock().unwrap();
+        if !sessions.iter().any(|known| Weak::ptr_eq(known, &session)) {
+            sessions.push(session);
         }
+    }
 
-        Ok(())
+    fn notify_sessions(&self, method: &str) {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|session| session.strong_count() > 0);
+        for session in sessions.iter().filter_map(Weak::upgrade) {
+            if !session.is_initialized() {
+                continue;
+            }
+            let notification = McpNotification {
+                jsonrpc: "2.0".to_string(),
+                method: method.to_string(),
+                params: None,
+            };
+            if !session.notify(notification) {
+                debug!("Session has no channel for {}", method);
+            }
+        }
     }
 
-    async fn handle_request(&mut self, request: McpRequest) -> McpResponse {
+    async fn handle_request(
+        &self,
+        session: &Session,
+        request: McpRequest,
+        sender: &MessageSender,
+    ) -> McpResponse {
         let id = Self::ensure_valid_id(request.id.clone());
 
         match request.method.as_str() {
-            "initialize" => self.handle_initialize(request).await,
-            "tools/list" => self.handle_list_tools(request).await,
-            "tools/call" => self.handle_call_tool(request).await,
+            "initialize" => self.handle_initialize(session, request).await,
+            "tools/list" => self.handle_list_tools(session, request).await,
+            "tools/call" => self.handle_call_tool(session, request, sender).await,
+            "resources/list" => self.handle_list_resources(session, request).await,
+            "resources/templates/list" => self.handle_list_resource_templates(request).await,
+            "resources/read" => self.handle_read_resource(request).await,
+            "prompts/list" => self.handle_list_prompts(session, request).await,
+            "prompts/get" => self.handle_get_prompt(request).await,
+            "logging/setLevel" => self.handle_set_level(session, request).await,
             "ping" => self.handle_ping(request).await,
             _ => McpResponse {
                 result: None,
@@ -81,16 +186,24 @@ impl McpServer {
         }
     }
 
-    async fn handle_notification(&mut self, notification: McpNotification) {
+    fn handle_notification(&self, session: &Session, notification: McpNotification) {
         debug!("Received notification: {}", notification.method);
 
         match notification.method.as_str() {
             "notifications/initialized" => {
                 info!("Client initialization completed");
-                self.initialized = true;
+                session.set_initialized();
             }
             "notifications/cancelled" => {
-                debug!("Request cancelled notification received");
+                let params = notification
+                    .params
+                    .and_then(|p| serde_json::from_value::<CancelledParams>(p).ok());
+                match params {
+                    Some(params) => {
+                        Self::cancel_request(session, &params.request_id, params.reason)
+                    }
+                    None => warn!("Ignoring notifications/cancelled without a valid requestId"),
+                }
             }
             _ => {
                 warn!("Unknown notification method: {}", notification.method);
@@ -98,6 +211,24 @@ impl McpServer {
         }
     }
 
+    /// Aborts the task handling the given request. Dropping its future stops any
+    /// in-progress HTTP body or SSE stream; no response is sent for it.
+    fn cancel_request(session: &Session, request_id: &serde_json::Value, reason: Option<String>) {
+        if session.cancel_request(request_id) {
+            info!(
+                "Cancelled request {} ({})",
+                request_id,
+                reason.as_deref().unwrap_or("no reason given")
+            );
+        } else {
+            // Already finished or unknown id; the spec says to ignore it
+            debug!(
+                "Cancellation for unknown or completed request {}",
+                request_id
+            );
+        }
+    }
+
     fn ensure_valid_id(id: Option<serde_json::Value>) -> serde_json::Value {
         match id {
             Some(value) => match value {
@@ -108,16 +239,31 @@ impl McpServer {
         }
     }
 
-    async fn handle_initialize(&mut self, request: McpRequest) -> McpResponse {
+    async fn handle_initialize(&self, session: &Session, request: McpRequest) -> McpResponse {
         let id = Self::ensure_valid_id(request.id.clone());
 
         match request.params {
             Some(params) => match serde_json::from_value::<InitializeParams>(params) {
-                Ok(_init_params) => {
+                Ok(init_params) => {
+                    let version = ProtocolVersion::negotiate(&init_params.protocol_version);
+                    if version.as_str() == init_params.protocol_version {
+                        info!("Negotiated protocol version {}", version.as_str());
+                    } else {
+                        warn!(
+                            "Client requested unsupported protocol version {}, offering {}",
+                            init_params.protocol_version,
+                            version.as_str()
+                        );
+                    }
+                    session.set_protocol_version(version);
+
                     let result = InitializeResult {
-                            protocol_version: "2024-11-05".to_string(),
+                            protocol_version: version.as_str().to_string(),
                             server_info: ServerInfo {
                                 name: "DuckDuckGo, Google Search & Felo AI Search MCP".to_string(),
+                                title: version
+                                    .supports_titles()
+                                    .then(|| "Web Search & Fetch".to_string()),
                                 version: "1.1.1".to_string(),
                                 description: Some("A Model Context Protocol server for web search using DuckDuckGo, Google Search, and Felo AI".to_string()),
                             },
@@ -125,6 +271,11 @@ impl McpServer {
                                 tools: Some(ToolsCapability {
                                     list_changed: Some(true),
                                 }),
+                                resources: Some(ResourcesCapability {
+                                    subscribe: None,
+                                    list_changed: None,
+                                }),
+                                prompts: Some(PromptsCapability { list_changed: None }),
                                 logging: Some(serde_json::json!({})),
                             },
                         };
@@ -160,25 +311,38 @@ impl McpServer {
         }
     }
 
-    async fn handle_list_tools(&self, request: McpRequest) -> McpResponse {
+    /// Tools offered with the current settings.
+    fn listed_tools(&self) -> Vec<ToolDefinition> {
+        let settings = self.tool_settings();
         let mut tools = vec![
             DUCKDUCKGO_SEARCH_TOOL_DEFINITION.clone(),
-            FETCH_URL_TOOL_DEFINITION.clone(),
+            fetch_url_tool_definition(),
             METADATA_TOOL_DEFINITION.clone(),
             FELO_TOOL_DEFINITION.clone(),
             URL_FETCH_TOOL_DEFINITION.clone(),
         ];
 
         // Add Google Search tool if configured
-        if self.google_config.is_some() {
+        if settings.google_config.is_some() {
             tools.push(GOOGLE_SEARCH_TOOL_DEFINITION.clone());
         }
 
         // Add Jina Reader tool if configured
-        if self.jina_api_key.is_some() {
-            tools.push(JINA_READER_TOOL_DEFINITION.clone());
+        if settings.jina_api_key.is_some() {
+            tools.push(jina_reader_tool_definition());
         }
 
+        tools.retain(|tool| !settings.disabled_tools.contains(&tool.name));
+        tools
+    }
+
+    async fn handle_list_tools(&self, session: &Session, request: McpRequest) -> McpResponse {
+        let version = session.protocol_version();
+        let tools = self
+            .listed_tools()
+            .into_iter()
+            .map(|tool| version.tool_definition(tool))
+            .collect();
         let result = ListToolsResult { tools };
 
         McpResponse {
@@ -189,13 +353,42 @@ impl McpServer {
         }
     }
 
-    async fn handle_call_tool(&self, request: McpRequest) -> McpResponse {
+    async fn handle_call_tool(
+        &self,
+        session: &Session,
+        request: McpRequest,
+        sender: &MessageSender,
+    ) -> McpResponse {
         let id = Self::ensure_valid_id(request.id.clone());
 
         match request.params {
             Some(params) => match serde_json::from_value::<CallToolParams>(params) {
                 Ok(call_params) => {
-                    let result = self.execute_tool(call_params).await;
+                    let progress_sink = call_params
+                        .meta
+                        .as_ref()
+                        .and_then(|meta| meta.progress_token.clone())
+                        .map(|token| Self::progress_sink(token, sender.clone()));
+
+                    let result = match self.tool_call_slots.acquire().await {
+                        Ok(_permit) => {
+                            let (mut result, stats) = retry::track(with_progress(
+                                progress_sink,
+                                self.execute_tool(call_params),
+                            ))
+                            .await;
+                            if stats.requests > 0 {
+                                result.meta = Some(serde_json::json!({
+                                    "requests": stats.requests,
+                                    "attempts": stats.attempts,
+                                    "retries": stats.attempts - stats.requests,
+                                }));
+                            }
+                            result
+                        }
+                        Err(_) => CallToolResult::error("Server is shutting down"),
+                    };
+                    let result = session.protocol_version().call_tool_result(result);
                     McpResponse {
                         result: Some(serde_json::to_value(result).unwrap()),
                         error: None,
@@ -227,6 +420,264 @@ impl McpServer {
         }
     }
 
+    /// Builds a sink that turns progress updates into `notifications/progress` for the given token.
+    fn progress_sink(token: serde_json::Value, sender: MessageSender) -> ProgressSink {
+        Arc::new(move |progress, total, message| {
+            let params = ProgressParams {
+                progress_token: token.clone(),
+                progress,
+                total,
+                message,
+            };
+            let notification = McpNotification {
+                jsonrpc: "2.0".to_string(),
+                method: "notifications/progress".to_string(),
+                params: Some(serde_json::to_value(params).unwrap()),
+            };
+            if let Err(e) = sender.send_notification(notification) {
+                debug!("Failed to send progress notification: {}", e);
+            }
+        })
+    }
+
+    async fn handle_list_resources(&self, session: &Session, request: McpRequest) -> McpResponse {
+        let supports_titles = session.protocol_version().supports_titles();
+        let resources = page_store::list_pages()
+            .into_iter()
+            .map(|page| Resource {
+                uri: page.uri(),
+                name: page.title.clone().unwrap_or_else(|| page.url.clone()),
+                title: page.title.filter(|_| supports_titles),
+                description: Some(format!("Markdown of {}", page.url)),
+                mime_type: Some("text/markdown".to_string()),
+                size: Some(page.markdown.len() as u64),
+            })
+            .collect();
+
+        McpResponse {
+            result: Some(serde_json::to_value(ListResourcesResult { resources }).unwrap()),
+            error: None,
+            jsonrpc: "2.0".to_string(),
+            id: Self::ensure_valid_id(request.id),
+        }
+    }
+
+    async fn handle_list_resource_templates(&self, request: McpRequest) -> McpResponse {
+        // The template fetches arbitrary pages, which is what disabling url-fetch forbids
+        let fetch_enabled = !self.tool_settings().disabled_tools.contains("url-fetch");
+        let result = ListResourceTemplatesResult {
+            resource_templates: [ResourceTemplate {
+                uri_template: RESOURCE_TEMPLATE.to_string(),
+                name: "Web page".to_string(),
+                title: None,
+                description: Some(
+                    "Any http(s) URL fetched and converted to markdown, like the url-fetch tool"
+                        .to_string(),
+                ),
+                mime_type: Some("text/markdown".to_string()),
+            }]
+            .into_iter()
+            .filter(|_| fetch_enabled)
+            .collect(),
+        };
+
+        McpResponse {
+            result: Some(serde_json::to_value(result).unwrap()),
+            error: None,
+            jsonrpc: "2.0".to_string(),
+            id: Self::ensure_valid_id(request.id),
+        }
+    }
+
+    /// Serves `web://{url}` from the page store, fetching (and storing) the page on a miss.
+    async fn handle_read_resource(&self, request: McpRequest) -> McpResponse {
+        let id = Self::ensure_valid_id(request.id.clone());
+
+        let params = match request
+            .params
+            .map(serde_json::from_value::<ReadResourceParams>)
+        {
+            Some(Ok(params)) => params,
+            Some(Err(e)) => {
+                return Self::error_response(id, -32602, format!("Invalid params: {}", e), None)
+            }
+            None => return Self::error_response(id, -32602, "Missing params".to_string(), None),
+        };
+
+        let url = match page_store::parse_resource_uri(&params.uri) {
+            Some(url) => url,
+            None => {
+                return Self::error_response(
+                    id,
+                    -32002,
+                    "Resource not found".to_string(),
+                    Some(serde_json::json!({ "uri": params.uri })),
+                )
+            }
+        };
+
+        let markdown = match page_store::get_page(&url) {
+            Some(page) => {
+                // The domain policy may have changed since the page was stored
+                let parsed = url::Url::parse(&url).expect("resource URIs hold valid URLs");
+                if let Err(e) = domain_policy::check_url(&parsed) {
+                    let mut data = e.to_json();
+                    data["uri"] = serde_json::Value::String(params.uri);
+                    return Self::error_response(
+                        id,
+                        -32603,
+                        format!("Failed to read resource: {}", e),
+                        Some(data),
+                    );
+                }
+                page.markdown
+            }
+            // Stored pages stay readable, but nothing new is fetched
+            None if self.tool_settings().disabled_tools.contains("url-fetch") => {
+                return Self::error_response(
+                    id,
+                    -32002,
+                    "Resource not found".to_string(),
+                    Some(serde_json::json!({ "uri": params.uri })),
+                )
+            }
+            None => {
+                let _permit = match self.tool_call_slots.acquire().await {
+                    Ok(permit) => permit,
+                    Err(_) => {
+                        return Self::error_response(
+                            id,
+                            -32603,
+                            "Server is shutting down".to_string(),
+                            None,
+                        )
+                    }
+                };
+                match UrlFetchTool::fetch_markdown(&url).await {
+                    Ok(markdown) => markdown,
+                    Err(e) => {
+                        // Same code/details as the url-fetch tool error, plus the requested URI
+                        let mut data = e.to_json();
+                        data["uri"] = serde_json::Value::String(params.uri);
+                        return Self::error_response(
+                            id,
+                            -32603,
+                            format!("Failed to fetch resource: {}", e),
+                            Some(data),
+                        );
+                    }
+                }
+            }
+        };
+
+        let result = ReadResourceResult {
+            contents: vec![ResourceContents {
+                uri: params.uri,
+                mime_type: Some("text/markdown".to_string()),
+                text: markdown,
+            }],
+        };
+
+        McpResponse {
+            result: Some(serde_json::to_value(result).unwrap()),
+            error: None,
+            jsonrpc: "2.0".to_string(),
+            id,
+        }
+    }
+
+    async fn handle_list_prompts(&self, session: &Session, request: McpRequest) -> McpResponse {
+        let supports_titles = session.protocol_version().supports_titles();
+        let prompts = PROMPT_DEFINITIONS
+            .iter()
+            .cloned()
+            .map(|mut prompt| {
+                if !supports_titles {
+                    prompt.title = None;
+                }
+                prompt
+            })
+            .collect();
+
+        McpResponse {
+            result: Some(serde_json::to_value(ListPromptsResult { prompts }).unwrap()),
+            error: None,
+            jsonrpc: "2.0".to_string(),
+            id: Self::ensure_valid_id(request.id),
+        }
+    }
+
+    async fn handle_get_prompt(&self, request: McpRequest) -> McpResponse {
+        let id = Self::ensure_valid_id(request.id.clone());
+
+        let params = match request
+            .params
+            .map(serde_json::from_value::<GetPromptParams>)
+        {
+            Some(Ok(params)) => params,
+            Some(Err(e)) => {
+                return Self::error_response(id, -32602, format!("Invalid params: {}", e), None)
+            }
+            None => return Self::error_response(id, -32602, "Missing params".to_string(), None),
+        };
+
+        let settings = self.tool_settings();
+        let tools = PromptTools {
+            google_search: settings.google_config.is_some()
+                && !settings.disabled_tools.contains("google-search"),
+            jina_reader: settings.jina_api_key.is_some()
+                && !settings.disabled_tools.contains("jina-reader"),
+        };
+        match prompts::get_prompt(&params.name, &params.arguments, &tools) {
+            Ok(result) => McpResponse {
+                result: Some(serde_json::to_value(result).unwrap()),
+                error: None,
+                jsonrpc: "2.0".to_string(),
+                id,
+            },
+            Err(message) => Self::error_response(id, -32602, message, None),
+        }
+    }
+
+    async fn handle_set_level(&self, session: &Session, request: McpRequest) -> McpResponse {
+        let id = Self::ensure_valid_id(request.id.clone());
+
+        match request.params.map(serde_json::from_value::<SetLevelParams>) {
+            Some(Ok(params)) => {
+                info!("Client log level set to {:?}", params.level);
+                session.set_log_level(params.level);
+                McpResponse {
+                    result: Some(serde_json::json!({})),
+                    error: None,
+                    jsonrpc: "2.0".to_string(),
+                    id,
+                }
+            }
+            Some(Err(e)) => {
+                Self::error_response(id, -32602, format!("Invalid params: {}", e), None)
+            }
+            None => Self::error_response(id, -32602, "Missing params".to_string(), None),
+        }
+    }
+
+    fn error_response(
+        id: serde_json::Value,
+        code: i32,
+        message: String,
+        data: Option<serde_json::Value>,
+    ) -> McpResponse {
+        McpResponse {
+            result: None,
+            error: Some(McpError {
+                code,
+                message,
+                data,
+            }),
+            jsonrpc: "2.0".to_string(),
+            id,
+        }
+    }
+
     async fn handle_ping(&self, request: McpRequest) -> McpResponse {
         let id = Self::ensure_valid_id(request.id.clone());
 
@@ -239,13 +690,30 @@ impl McpServer {
     }
 
     async fn execute_tool(&self, params: CallToolParams) -> CallToolResult {
+        // Every tool accepts `noCache`; it applies to all cache lookups made by the call
+        let no_cache = params
+            .arguments
+            .as_ref()
+            .and_then(|args| args.get("noCache"))
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
+        cache::with_no_cache(no_cache, self.dispatch_tool(params)).await
+    }
+
+    async fn dispatch_tool(&self, params: CallToolParams) -> CallToolResult {
+        let settings = self.tool_settings();
+        if settings.disabled_tools.contains(&params.name) {
+            return CallToolResult::error(format!("Tool is disabled: {}", params.name));
+        }
+        // Search tools stay separate arms: their schemas and output differ beyond the shared
+        // `SearchRequest`, so each tool maps its own arguments onto a `SearchProvider`
         match params.name.as_str() {
             "duckduckgo-search" => {
                 let tool = DuckDuckGoSearchTool::new();
                 tool.execute(params.arguments).await
             }
             "google-search" => {
-                let (api_key, search_engine_id) = if let Some(ref config) = self.google_config {
+                let (api_key, search_engine_id) = if let Some(ref config) = settings.google_config {
                     (
                         Some(config.api_key.clone()),
                         Some(config.search_engine_id.clone()),
@@ -269,7 +737,7 @@ impl McpServer {
                 tool.execute(params.arguments).await
             }
             "jina-reader" => {
-                let tool = JinaReaderTool::new(self.jina_api_key.clone());
+                let tool = JinaReaderTool::new(settings.jina_api_key);
                 tool.execute(params.arguments).await
             }
             "url-fetch" => {
@@ -280,3 +748,133 @@ impl McpServer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_request(url: &str) -> McpRequest {
+        McpRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(1)),
+            method: "resources/read".to_string(),
+            params: Some(serde_json::json!({ "uri": page_store::resource_uri(url) })),
+        }
+    }
+
+    fn request(id: u64, method: &str, params: Option<serde_json::Value>) -> McpMessage {
+        McpMessage::Request(McpRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(id)),
+            method: method.to_string(),
+            params,
+        })
+    }
+
+    #[tokio::test]
+    async fn tool_calls_wait_for_a_slot_but_pings_do_not() {
+        let server = Arc::new(McpServer::new(ToolSettings::default(), 1));
+        let session = Arc::new(Session::new());
+        let (sender, mut rx) = MessageSender::channel();
+        let busy = server.tool_call_slots.acquire().await.unwrap();
+
+        let call = request(
+            1,
+            "tools/call",
+            Some(serde_json::json!({ "name": "no-such-tool" })),
+        );
+        Arc::clone(&server).handle_message(&session, call, sender.clone());
+        Arc::clone(&server).handle_message(&session, request(2, "ping", None), sender);
+
+        let next = |line: Option<String>| {
+            serde_json::from_str::<serde_json::Value>(&line.unwrap()).unwrap()["id"].clone()
+        };
+        assert_eq!(next(rx.recv().await), 2);
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert!(rx.try_recv().is_err());
+
+        drop(busy);
+        assert_eq!(next(rx.recv().await), 1);
+    }
+
+    #[tokio::test]
+    async fn sessions_are_registered_once_after_a_successful_initialize() {
+        let server = Arc::new(McpServer::new(ToolSettings::default(), 1));
+        let session = Arc::new(Session::new());
+        let (sender, mut rx) = MessageSender::channel();
+        let params = serde_json::json!({
+            "protocolVersion": "2025-06-18",
+            "capabilities": {},
+            "clientInfo": { "name": "test", "version": "1" }
+        });
+
+        let failed = request(1, "initialize", Some(serde_json::json!({})));
+        Arc::clone(&server).handle_message(&session, failed, sender.clone());
+        rx.recv().await.unwrap();
+        assert!(server.sessions.lock().unwrap().is_empty());
+
+        for id in 2..4 {
+            let initialize = request(id, "initialize", Some(params.clone()));
+            Arc::clone(&server).handle_message(&session, initialize, sender.clone());
+            rx.recv().await.unwrap();
+        }
+        assert_eq!(server.sessions.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn disabled_url_fetch_serves_only_stored_pages() {
+        let server = McpServer::new(
+            ToolSettings {
+                disabled_tools: HashSet::from(["url-fetch".to_string()]),
+                ..Default::default()
+            },
+            1,
+        );
+        page_store::store_page("https://stored.example/", "# Stored");
+
+        let hit = server
+            .handle_read_resource(read_request("https://stored.example/"))
+            .await;
+        assert!(hit.error.is_none());
+
+        // Never reaches the network: a miss is reported instead of fetched
+        let miss = server
+            .handle_read_resource(read_request("https://missing.example/"))
+            .await;
+        assert_eq!(miss.error.unwrap().code, -32002);
+
+        let templates = server
+            .handle_list_resource_templates(read_request("https://stored.example/"))
+            .await;
+        assert_eq!(
+            templates.result.unwrap()["resourceTemplates"],
+            serde_json::json!([])
+        );
+    }
+
+    #[tokio::test]
+    async fn stored_pages_follow_the_current_domain_policy() {
+        let server = McpServer::new(ToolSettings::default(), 1);
+        page_store::store_page("https://revoked.example/", "# Revoked");
+        let policy = domain_policy::DomainPolicy {
+            allow: Vec::new(),
+            deny: vec!["revoked.example".parse().unwrap()],
+        };
+
+        let response = domain_policy::with_private_policy(
+            policy,
+            server.handle_read_resource(read_request("https://revoked.example/")),
+        )
+        .await;
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32603);
+        let data = error.data.unwrap();
+        assert_eq!(data["code"], "ERR_FETCH_POLICY");
+        assert_eq!(
+            data["uri"],
+            page_store::resource_uri("https://revoked.example/")
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::Result;
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+use uuid::Uuid;
+
+use super::dispatch::{McpHandler, Session};
+use super::streamable_http::{origin_allowed, rpc_error, shutdown_signal};
+use super::transport::{parse_message, MessageSender};
+
+const SSE_PATH: &str = "/sse";
+const MESSAGES_PATH: &str = "/messages";
+
+// One client of the legacy transport: its session and the sender feeding its SSE stream
+struct SseSession {
+    session: Arc<Session>,
+    sender: MessageSender,
+}
+
+type Sessions = Arc<Mutex<HashMap<String, SseSession>>>;
+
+#[derive(Clone)]
+struct AppState {
+    handler: Arc<dyn McpHandler>,
+    sessions: Sessions,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesQuery {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+}
+
+// Removes the session when its SSE stream is dropped (client disconnected)
+struct SessionGuard {
+    session_id: String,
+    sessions: Sessions,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        if let Some(sse_session) = self.sessions.lock().unwrap().remove(&self.session_id) {
+            sse_session.session.close();
+            info!(
+                "SSE client disconnected, closed session {}",
+                self.session_id
+            );
+        }
+    }
+}
+
+/// Serves the legacy HTTP+SSE transport (protocol revision 2024-11-05) on `addr`.
+/// GET /sse opens the event stream and announces the message endpoint; clients then
+/// POST JSON-RPC messages to /messages?sessionId=... and receive replies on the stream.
+pub async fn serve(handler: Arc<dyn McpHandler>, addr: SocketAddr) -> Result<()> {
+    let state = AppState {
+        handler,
+        sessions: Arc::new(Mutex::new(HashMap::new())),
+    };
+
+    let app = Router::new()
+        .route(SSE_PATH, get(handle_sse))
+        .route(MESSAGES_PATH, post(handle_message))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!(
+        "MCP server listening on http://{}{} (legacy SSE transport)",
+        listener.local_addr()?,
+        SSE_PATH
+    );
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    Ok(())
+}
+
+async fn handle_sse(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if !origin_allowed(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let session_id = Uuid::new_v4().to_string();
+    let (sender, rx) = MessageSender::channel();
+    let session = Arc::new(Session::new());
+    session.set_notifier(Some(sender.clone()));
+    state
+        .sessions
+        .lock()
+        .unwrap()
+        .insert(session_id.clone(), SseSession { session, sender });
+    info!("Opened SSE session {}", session_id);
+
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("{}?sessionId={}", MESSAGES_PATH, session_id));
+    let guard = SessionGuard {
+        session_id,
+        sessions: Arc::clone(&state.sessions),
+    };
+
+    let messages = futures::stream::unfold((rx, guard), |(mut rx, guard)| async move {
+        rx.recv().await.map(|line| {
+            (
+                Ok::<_, Infallible>(Event::default().event("message").data(line)),
+                (rx, guard),
+            )
+        })
+    });
+    let stream = futures::StreamExt::chain(
+        futures::stream::once(async move { Ok::<_, Infallible>(endpoint) }),
+        messages,
+    );
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+async fn handle_message(
+    State(state): State<AppState>,
+    Query(query): Query<MessagesQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if !origin_allowed(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let (session, sender) = match state.sessions.lock().unwrap().get(&query.session_id) {
+        Some(sse_session) => (Arc::clone(&sse_session.session), sse_session.sender.clone()),
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let value = match serde_json::from_slice::<serde_json::Value>(&body) {
+        Ok(value) => value,
+        Err(e) => {
+            return rpc_error(
+                StatusCode::BAD_REQUEST,
+                -32700,
+                format!("Parse error: {}", e),
+            )
+        }
+    };
+
+    // Replies are delivered over the SSE stream; the POST itself is only acknowledged
+    match parse_message(value) {
+        Ok(message) => {
+            Arc::clone(&state.handler).handle_message(&session, message, sender);
+            StatusCode::ACCEPTED.into_response()
+        }
+        Err(e) => rpc_error(StatusCode::BAD_REQUEST, -32600, e.to_string()),
+    }
+}
@@ -0,0 +1,562 @@
+use anyhow::Result;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::post,
+    Json, Router,
+};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use super::dispatch::{McpHandler, Session};
+use super::protocol::ProtocolVersion;
+use super::transport::{parse_message, MessageSender};
+use super::types::{McpError, McpMessage, McpResponse};
+
+const MCP_PATH: &str = "/mcp";
+const SESSION_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+// Sessions without requests for this long are terminated; the client gets 404 and re-initializes
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+// Further `initialize` requests are refused while this many sessions are open
+const MAX_SESSIONS: usize = 1000;
+
+#[derive(Debug, Clone, Copy)]
+struct SessionLimits {
+    idle_timeout: Duration,
+    max_sessions: usize,
+}
+
+impl Default for SessionLimits {
+    fn default() -> Self {
+        Self {
+            idle_timeout: SESSION_IDLE_TIMEOUT,
+            max_sessions: MAX_SESSIONS,
+        }
+    }
+}
+
+struct SessionEntry {
+    // The session's notifier is the standalone GET stream, once the client opened one
+    session: Arc<Session>,
+    last_active: Instant,
+}
+
+impl SessionEntry {
+    // A session with an open GET stream is in use even without requests
+    fn expired(&self, now: Instant, idle_timeout: Duration) -> bool {
+        now.duration_since(self.last_active) >= idle_timeout && !self.session.has_notifier()
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    handler: Arc<dyn McpHandler>,
+    sessions: Arc<Mutex<HashMap<String, SessionEntry>>>,
+    limits: SessionLimits,
+}
+
+/// Serves the MCP Streamable HTTP transport on `addr`.
+/// POST /mcp carries JSON-RPC messages, GET /mcp opens an optional SSE stream for
+/// server notifications, and DELETE /mcp terminates the session.
+pub async fn serve(handler: Arc<dyn McpHandler>, addr: SocketAddr) -> Result<()> {
+    let app = router(handler, SessionLimits::default());
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!(
+        "MCP server listening on http://{}{}",
+        listener.local_addr()?,
+        MCP_PATH
+    );
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    Ok(())
+}
+
+fn router(handler: Arc<dyn McpHandler>, limits: SessionLimits) -> Router {
+    let state = AppState {
+        handler,
+        sessions: Arc::new(Mutex::new(HashMap::new())),
+        limits,
+    };
+    Router::new()
+        .route(
+            MCP_PATH,
+            post(handle_post).get(handle_get).delete(handle_delete),
+        )
+        .with_state(state)
+}
+
+async fn handle_post(State(state): State<AppState>, headers: HeaderMap, body: Bytes) -> Response {
+    if !origin_allowed(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let value = match serde_json::from_slice::<serde_json::Value>(&body) {
+        Ok(value) => value,
+        Err(e) => {
+            return rpc_error(
+                StatusCode::BAD_REQUEST,
+                -32700,
+                format!("Parse error: {}", e),
+            )
+        }
+    };
+
+    // JSON-RPC batches arrive as arrays; a single message is treated as a batch of one
+    let is_batch = value.is_array();
+    let values = match value {
+        serde_json::Value::Array(values) => values,
+        value => vec![value],
+    };
+
+    let mut messages = Vec::with_capacity(values.len());
+    for value in values {
+        // Responses to server-initiated requests carry no method; we never send such requests
+        if value.get("method").is_none() {
+            debug!("Ignoring client JSON-RPC response");
+            continue;
+        }
+        match parse_message(value) {
+            Ok(message) => messages.push(message),
+            Err(e) => return rpc_error(StatusCode::BAD_REQUEST, -32600, e.to_string()),
+        }
+    }
+
+    let is_initialize = messages
+        .iter()
+        .any(|m| matches!(m, McpMessage::Request(r) if r.method == "initialize"));
+
+    let (session_id, session) = if is_initialize {
+        let mut sessions = state.sessions.lock().unwrap();
+        expire_idle(&mut sessions, state.limits.idle_timeout);
+        if sessions.len() >= state.limits.max_sessions {
+            warn!(
+                "Refusing new HTTP session: {} sessions open",
+                sessions.len()
+            );
+            return rpc_error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                -32000,
+                "Too many open sessions".to_string(),
+            );
+        }
+        let session_id = Uuid::new_v4().to_string();
+        let session = Arc::new(Session::new());
+        sessions.insert(
+            session_id.clone(),
+            SessionEntry {
+                session: Arc::clone(&session),
+                last_active: Instant::now(),
+            },
+        );
+        drop(sessions);
+        info!("Created HTTP session {}", session_id);
+        (session_id, session)
+    } else {
+        match lookup_session(&state, &headers) {
+            Ok(found) => found,
+            Err(response) => return response,
+        }
+    };
+
+    let (sender, rx) = MessageSender::channel();
+    let mut has_requests = false;
+    for message in messages {
+        has_requests |= matches!(message, McpMessage::Request(_));
+        Arc::clone(&state.handler).handle_message(&session, message, sender.clone());
+    }
+    // Request tasks hold the remaining senders; the stream ends when the last one responds
+    drop(sender);
+
+    let mut response = if !has_requests {
+        StatusCode::ACCEPTED.into_response()
+    } else if accepts(&headers, "text/event-stream") {
+        sse_response(rx)
+    } else {
+        collect_json_response(rx, is_batch, &session).await
+    };
+
+    if let Ok(value) = HeaderValue::from_str(&session_id) {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    response
+}
+
+async fn handle_get(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if !origin_allowed(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    if !accepts(&headers, "text/event-stream") {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+
+    let (_, session) = match lookup_session(&state, &headers) {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    let (sender, rx) = MessageSender::channel();
+    // A newer stream replaces the previous one, which then ends
+    session.set_notifier(Some(sender));
+
+    sse_response(rx)
+}
+
+async fn handle_delete(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if !origin_allowed(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let session_id = match headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(id) => id.to_string(),
+        None => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    let mut sessions = state.sessions.lock().unwrap();
+    expire_idle(&mut sessions, state.limits.idle_timeout);
+    match sessions.remove(&session_id) {
+        Some(entry) => {
+            entry.session.close();
+            info!("Terminated HTTP session {}", session_id);
+            StatusCode::OK.into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn lookup_session(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(String, Arc<Session>), Response> {
+    let session_id = headers
+        .get(SESSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| {
+            rpc_error(
+                StatusCode::BAD_REQUEST,
+                -32600,
+                "Missing Mcp-Session-Id header".to_string(),
+            )
+        })?;
+
+    // Clients on 2025-06-18 and later repeat the negotiated revision on every request
+    let requested_version = match headers.get(PROTOCOL_VERSION_HEADER) {
+        Some(version) => {
+            let version = version.to_str().unwrap_or_default();
+            match ProtocolVersion::parse(version) {
+                Some(parsed) => Some(parsed),
+                None => {
+                    return Err(rpc_error(
+                        StatusCode::BAD_REQUEST,
+                        -32600,
+                        format!("Unsupported MCP-Protocol-Version: {}", version),
+                    ))
+                }
+            }
+        }
+        None => None,
+    };
+
+    let mut sessions = state.sessions.lock().unwrap();
+    expire_idle(&mut sessions, state.limits.idle_timeout);
+    // Unknown, expired or terminated session: the client must re-initialize
+    let entry = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+
+    let negotiated = entry.session.protocol_version();
+    if requested_version.is_some_and(|version| version != negotiated) {
+        return Err(rpc_error(
+            StatusCode::BAD_REQUEST,
+            -32600,
+            format!(
+                "MCP-Protocol-Version does not match the negotiated version {}",
+                negotiated.as_str()
+            ),
+        ));
+    }
+
+    entry.last_active = Instant::now();
+    Ok((session_id.to_string(), Arc::clone(&entry.session)))
+}
+
+// Terminates the sessions that have been idle for `idle_timeout`
+fn expire_idle(sessions: &mut HashMap<String, SessionEntry>, idle_timeout: Duration) {
+    let now = Instant::now();
+    sessions.retain(|session_id, entry| {
+        if !entry.expired(now, idle_timeout) {
+            return true;
+        }
+        entry.session.close();
+        info!("Expired idle HTTP session {}", session_id);
+        false
+    });
+}
+
+pub(super) async fn shutdown_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+    info!("Shutting down HTTP transport");
+}
+
+pub(super) fn sse_response(rx: mpsc::UnboundedReceiver<String>) -> Response {
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv()
+            .await
+            .map(|line| (Ok::<_, Infallible>(Event::default().data(line)), rx))
+    });
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Waits for all responses of a POST and returns them as a plain JSON body.
+/// Notifications (e.g. progress) cannot be carried in JSON mode and are forwarded
+/// to the session's GET stream when one is open.
+async fn collect_json_response(
+    mut rx: mpsc::UnboundedReceiver<String>,
+    is_batch: bool,
+    session: &Session,
+) -> Response {
+    let mut responses = Vec::new();
+    while let Some(line) = rx.recv().await {
+        let value = match serde_json::from_str::<serde_json::Value>(&line) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        if value.get("id").is_some() {
+            responses.push(value);
+        } else if let Ok(notification) = serde_json::from_value(value) {
+            session.notify(notification);
+        }
+    }
+
+    if is_batch {
+        Json(serde_json::Value::Array(responses)).into_response()
+    } else {
+        match responses.pop() {
+            Some(response) => Json(response).into_response(),
+            // The only request was cancelled before responding
+            None => StatusCode::ACCEPTED.into_response(),
+        }
+    }
+}
+
+pub(super) fn rpc_error(status: StatusCode, code: i32, message: String) -> Response {
+    let body = McpResponse {
+        result: None,
+        error: Some(McpError {
+            code,
+            message,
+            data: None,
+        }),
+        jsonrpc: "2.0".to_string(),
+        id: serde_json::Value::Null,
+    };
+    (status, Json(body)).into_response()
+}
+
+pub(super) fn accepts(headers: &HeaderMap, mime: &str) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.contains(mime) || v.contains("*/*"))
+}
+
+/// Rejects cross-site browser requests (DNS rebinding protection).
+/// Requests without an Origin header (non-browser clients) are allowed.
+pub(super) fn origin_allowed(headers: &HeaderMap) -> bool {
+    let origin = match headers.get(header::ORIGIN).and_then(|v| v.to_str().ok()) {
+        Some(origin) => origin,
+        None => return true,
+    };
+
+    let origin_host = match url::Url::parse(origin)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+    {
+        Some(host) => host,
+        None => {
+            warn!("Rejecting request with invalid Origin: {}", origin);
+            return false;
+        }
+    };
+
+    if matches!(origin_host.as_str(), "localhost" | "127.0.0.1" | "[::1]") {
+        return true;
+    }
+
+    let allowed = headers
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|host| url::Url::parse(&format!("http://{}", host)).ok())
+        .and_then(|u| u.host_str().map(|h| h == origin_host))
+        .unwrap_or(false);
+
+    if !allowed {
+        warn!("Rejecting request from foreign Origin: {}", origin);
+    }
+    allowed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::server::{McpServer, ToolSettings};
+    use reqwest::Client;
+    use serde_json::json;
+
+    async fn start(limits: SessionLimits) -> String {
+        let server = Arc::new(McpServer::new(ToolSettings::default(), 1));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(server, limits)).await });
+        format!("http://{}{}", addr, MCP_PATH)
+    }
+
+    fn client() -> Client {
+        Client::builder().no_proxy().build().unwrap()
+    }
+
+    async fn initialize(client: &Client, url: &str) -> reqwest::Response {
+        client
+            .post(url)
+            .header(header::ACCEPT, "application/json")
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "initialize",
+                "params": {
+                    "protocolVersion": "2025-06-18",
+                    "capabilities": {},
+                    "clientInfo": { "name": "test", "version": "1" }
+                }
+            }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    async fn list_tools(client: &Client, url: &str, session_id: &str, version: &str) -> StatusCode {
+        client
+            .post(url)
+            .header(header::ACCEPT, "application/json")
+            .header(SESSION_HEADER, session_id)
+            .header(PROTOCOL_VERSION_HEADER, version)
+            .json(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+            .send()
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn serves_a_session_over_post_get_and_delete() {
+        let url = start(SessionLimits::default()).await;
+        let client = client();
+
+        let response = initialize(&client, &url).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let session_id = response.headers()[SESSION_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["result"]["protocolVersion"], "2025-06-18");
+
+        let initialized = client
+            .post(&url)
+            .header(SESSION_HEADER, &session_id)
+            .json(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(initialized.status(), StatusCode::ACCEPTED);
+
+        assert_eq!(
+            list_tools(&client, &url, &session_id, "2025-06-18").await,
+            StatusCode::OK
+        );
+        // A revision other than the negotiated one, or none we know
+        assert_eq!(
+            list_tools(&client, &url, &session_id, "2025-03-26").await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            list_tools(&client, &url, &session_id, "1999-01-01").await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            list_tools(&client, &url, "no-such-session", "2025-06-18").await,
+            StatusCode::NOT_FOUND
+        );
+
+        let stream = client
+            .get(&url)
+            .header(header::ACCEPT, "text/event-stream")
+            .header(SESSION_HEADER, &session_id)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(stream.status(), StatusCode::OK);
+        assert_eq!(stream.headers()[header::CONTENT_TYPE], "text/event-stream");
+
+        let delete = || {
+            client
+                .delete(&url)
+                .header(SESSION_HEADER, &session_id)
+                .send()
+        };
+        assert_eq!(delete().await.unwrap().status(), StatusCode::OK);
+        assert_eq!(delete().await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            list_tools(&client, &url, &session_id, "2025-06-18").await,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn expires_idle_sessions_and_caps_their_number() {
+        let url = start(SessionLimits {
+            idle_timeout: Duration::from_millis(100),
+            max_sessions: 1,
+        })
+        .await;
+        let client = client();
+
+        let first = initialize(&client, &url).await;
+        let first_id = first.headers()[SESSION_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            initialize(&client, &url).await.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(initialize(&client, &url).await.status(), StatusCode::OK);
+        assert_eq!(
+            list_tools(&client, &url, &first_id, "2025-06-18").await,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
@@ -1,15 +1,58 @@
 use anyhow::Result;
 use futures::{SinkExt, StreamExt};
+use serde::Serialize;
 use serde_json;
+use std::sync::Arc;
 use tokio::io::BufReader;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio_util::codec::{FramedRead, FramedWrite, LinesCodec};
-use tracing::{debug, error};
+use tracing::{debug, error, info};
 
+use super::dispatch::{McpHandler, Session};
 use super::types::{McpMessage, McpNotification, McpRequest, McpResponse};
 
 pub struct StdioTransport {
     reader: FramedRead<BufReader<tokio::io::Stdin>, LinesCodec>,
-    writer: FramedWrite<tokio::io::Stdout, LinesCodec>,
+    sender: MessageSender,
+    writer_task: JoinHandle<()>,
+}
+
+/// Cloneable handle for queuing outgoing JSON-RPC messages.
+/// Messages are serialized by the caller and written one line at a time by a
+/// single writer task, so concurrent responses and notifications never interleave.
+#[derive(Clone)]
+pub struct MessageSender {
+    tx: mpsc::UnboundedSender<String>,
+}
+
+impl MessageSender {
+    pub fn channel() -> (Self, mpsc::UnboundedReceiver<String>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { tx }, rx)
+    }
+
+    pub fn send_response(&self, response: McpResponse) -> Result<()> {
+        self.send(&response)
+    }
+
+    pub fn send_notification(&self, notification: McpNotification) -> Result<()> {
+        self.send(&notification)
+    }
+
+    /// Whether the receiving end is gone, e.g. the client closed its stream.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    fn send<T: Serialize>(&self, message: &T) -> Result<()> {
+        let json = serde_json::to_string(message)?;
+        debug!("Sending: {}", json);
+
+        self.tx
+            .send(json)
+            .map_err(|_| anyhow::anyhow!("Transport writer is closed"))
+    }
 }
 
 impl StdioTransport {
@@ -18,9 +61,47 @@ impl StdioTransport {
         let stdout = tokio::io::stdout();
 
         let reader = FramedRead::new(BufReader::new(stdin), LinesCodec::new());
-        let writer = FramedWrite::new(stdout, LinesCodec::new());
+        let mut writer = FramedWrite::new(stdout, LinesCodec::new());
+        let (sender, mut rx) = MessageSender::channel();
+
+        let writer_task = tokio::spawn(async move {
+            while let Some(line) = rx.recv().await {
+                if let Err(e) = writer.send(line).await {
+                    error!("Error writing to stdout: {}", e);
+                    break;
+                }
+            }
+        });
+
+        Self {
+            reader,
+            sender,
+            writer_task,
+        }
+    }
+
+    /// Reads messages from stdio and dispatches them to `handler` as a single session.
+    /// Requests run concurrently and responses are written as soon as they are ready,
+    /// so they may arrive out of order; clients match them by JSON-RPC id.
+    pub async fn serve(mut self, handler: Arc<dyn McpHandler>) -> Result<()> {
+        info!("MCP server started and listening on stdio");
+
+        let session = Arc::new(Session::new());
+        session.set_notifier(Some(self.sender.clone()));
+
+        while let Some(message) = self.read_message().await? {
+            Arc::clone(&handler).handle_message(&session, message, self.sender.clone());
+        }
+        info!("Client disconnected");
+
+        // Let in-flight requests finish so their responses are not lost,
+        // then close the outgoing queue and wait until everything is written
+        session.wait_idle().await;
+        session.close();
+        drop(self.sender);
+        let _ = self.writer_task.await;
 
-        Self { reader, writer }
+        Ok(())
     }
 
     pub async fn read_message(&mut self) -> Result<Option<McpMessage>> {
@@ -30,35 +111,7 @@ impl StdioTransport {
 
                 // Parse as generic JSON first
                 match serde_json::from_str::<serde_json::Value>(&line) {
-                    Ok(value) => {
-                        // Check if this is a request (has id) or notification (no id)
-                        if let Some(obj) = value.as_object() {
-                            if obj.contains_key("id") {
-                                // This is a request
-                                match serde_json::from_value::<McpRequest>(value) {
-                                    Ok(request) => Ok(Some(McpMessage::Request(request))),
-                                    Err(e) => {
-                                        error!("Failed to parse request: {}", e);
-                                        Err(anyhow::anyhow!("Invalid JSON-RPC request: {}", e))
-                                    }
-                                }
-                            } else {
-                                // This is a notification
-                                match serde_json::from_value::<McpNotification>(value) {
-                                    Ok(notification) => {
-                                        Ok(Some(McpMessage::Notification(notification)))
-                                    }
-                                    Err(e) => {
-                                        error!("Failed to parse notification: {}", e);
-                                        Err(anyhow::anyhow!("Invalid JSON-RPC notification: {}", e))
-                                    }
-                                }
-                            }
-                        } else {
-                            error!("Invalid JSON-RPC message structure");
-                            Err(anyhow::anyhow!("Invalid JSON-RPC message structure"))
-                        }
-                    }
+                    Ok(value) => parse_message(value).map(Some),
                     Err(e) => {
                         error!("Failed to parse JSON: {}", e);
                         Err(anyhow::anyhow!("Invalid JSON: {}", e))
@@ -75,13 +128,32 @@ impl StdioTransport {
             }
         }
     }
+}
 
-    pub async fn write_response(&mut self, response: McpResponse) -> Result<()> {
-        let json = serde_json::to_string(&response)?;
-        debug!("Sending: {}", json);
-
-        self.writer.send(json).await?;
-
-        Ok(())
+/// Classifies a decoded JSON-RPC object as a request (has id) or a notification (no id).
+pub fn parse_message(value: serde_json::Value) -> Result<McpMessage> {
+    if let Some(obj) = value.as_object() {
+        if obj.contains_key("id") {
+            // This is a request
+            match serde_json::from_value::<McpRequest>(value) {
+                Ok(request) => Ok(McpMessage::Request(request)),
+                Err(e) => {
+                    error!("Failed to parse request: {}", e);
+                    Err(anyhow::anyhow!("Invalid JSON-RPC request: {}", e))
+                }
+            }
+        } else {
+            // This is a notification
+            match serde_json::from_value::<McpNotification>(value) {
+                Ok(notification) => Ok(McpMessage::Notification(notification)),
+                Err(e) => {
+                    error!("Failed to parse notification: {}", e);
+                    Err(anyhow::anyhow!("Invalid JSON-RPC notification: {}", e))
+                }
+            }
+        }
+    } else {
+        error!("Invalid JSON-RPC message structure");
+        Err(anyhow::anyhow!("Invalid JSON-RPC message structure"))
     }
 }
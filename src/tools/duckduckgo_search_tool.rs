@@ -4,10 +4,12 @@ use serde_json::json;
 use tracing::{error, info};
 
 use crate::mcp::types::{CallToolResult, ToolAnnotations, ToolDefinition};
-use crate::utils::duckduckgo_search::duckduckgo_search;
+use crate::utils::duckduckgo_search::DuckDuckGo;
+use crate::utils::search_provider::{self, SearchRequest, TimeRange};
 
 pub static DUCKDUCKGO_SEARCH_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| ToolDefinition {
     name: "duckduckgo-search".to_string(),
+    title: Some("DuckDuckGo Search".to_string()),
     description: "Search the web using DuckDuckGo and return results".to_string(),
     input_schema: json!({
         "type": "object",
@@ -28,10 +30,57 @@ pub static DUCKDUCKGO_SEARCH_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|
                 "default": 10,
                 "minimum": 1,
                 "maximum": 20
+            },
+            "site": {
+                "type": "string",
+                "description": "Limit results to a specific website domain (e.g., \"wikipedia.org\")"
+            },
+            "language": {
+                "type": "string",
+                "description": "Preferred result language as an ISO 639-1 code (e.g., \"en\", \"de\")"
+            },
+            "region": {
+                "type": "string",
+                "description": "Region as an ISO 3166-1 country code (e.g., \"us\", \"de\"); combined with language"
+            },
+            "timeRange": {
+                "type": "string",
+                "description": "Only results from the past day, week, month or year: \"d\", \"w\", \"m\" or \"y\", optionally with a count (e.g., \"d3\"); rounded up to one of these periods"
+            },
+            "noCache": {
+                "type": "boolean",
+                "description": "Skip cached results and fetch fresh ones (default: false)",
+                "default": false
             }
         },
         "required": ["query"]
     }),
+    output_schema: Some(json!({
+        "type": "object",
+        "properties": {
+            "query": { "type": "string" },
+            "page": { "type": "integer" },
+            "results": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "title": { "type": "string" },
+                        "url": { "type": "string" },
+                        "snippet": { "type": "string" },
+                        "display_url": { "type": "string" },
+                        "favicon": { "type": "string" }
+                    },
+                    "required": ["title", "url", "snippet", "display_url"]
+                }
+            },
+            "filtered": {
+                "type": "integer",
+                "description": "Results removed by the server's domain policy"
+            }
+        },
+        "required": ["query", "page", "results", "filtered"]
+    })),
     annotations: Some(ToolAnnotations {
         title: Some("DuckDuckGo Search".to_string()),
         read_only_hint: Some(true),
@@ -46,6 +95,11 @@ struct DuckDuckGoSearchParams {
     page: u32,
     #[serde(default = "default_num_results", rename = "numResults")]
     num_results: u32,
+    site: Option<String>,
+    language: Option<String>,
+    region: Option<String>,
+    #[serde(rename = "timeRange")]
+    time_range: Option<String>,
 }
 
 fn default_page() -> u32 {
@@ -78,40 +132,59 @@ impl DuckDuckGoSearchTool {
         };
 
         // Validate parameters
-        if params.num_results > 20 {
-            return CallToolResult::error("numResults cannot exceed 20");
+        if !(1..=20).contains(&params.num_results) {
+            return CallToolResult::error("numResults must be between 1 and 20");
         }
 
+        if params.page == 0 {
+            return CallToolResult::error("page must be at least 1");
+        }
+
+        let time_range = match params.time_range.as_deref().map(str::parse::<TimeRange>) {
+            Some(Err(e)) => return CallToolResult::error(format!("Invalid parameters: {}", e)),
+            Some(Ok(range)) => Some(range),
+            None => None,
+        };
+
         info!(
             "Searching for: {} (page {}, {} results)",
             params.query, params.page, params.num_results
         );
 
-        match duckduckgo_search(&params.query, params.page, params.num_results).await {
-            Ok(results) => {
-                info!("Found {} results", results.len());
+        let request = SearchRequest {
+            language: params.language,
+            region: params.region,
+            time_range,
+            site: params.site,
+            ..SearchRequest::new(params.query, params.page, params.num_results)
+        };
+        match search_provider::search(&DuckDuckGo, &request).await {
+            Ok(outcome) => {
+                let results = outcome.page.results;
+                let filtered = outcome.filtered;
+                let structured = json!({
+                    "query": request.query,
+                    "page": request.page,
+                    "results": results,
+                    "filtered": filtered,
+                });
 
                 if results.is_empty() {
-                    return CallToolResult::success("No results found.");
+                    return CallToolResult::success(format!(
+                        "No results found.{}",
+                        search_provider::filtered_note(filtered)
+                    ))
+                    .with_structured_content(structured);
                 }
 
-                // Format the results for display
-                let formatted_results = results
-                    .iter()
-                    .enumerate()
-                    .map(|(index, result)| {
-                        format!(
-                            "{}. [{}]({})\n   {}",
-                            index + 1,
-                            result.title,
-                            result.url,
-                            result.snippet
-                        )
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n\n");
-
-                CallToolResult::success(formatted_results)
+                let formatted_results = search_provider::format_results(&results)
+                    + &search_provider::filtered_note(filtered);
+                let mut result =
+                    CallToolResult::success(formatted_results).with_structured_content(structured);
+                result
+                    .content
+                    .extend(search_provider::resource_links(&results));
+                result
             }
             Err(e) => {
                 error!("Search error: {}", e);
@@ -9,6 +9,7 @@ use crate::utils::search_felo::search_felo;
 pub static FELO_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| {
     ToolDefinition {
     name: "felo-search".to_string(),
+    title: Some("Felo AI Search".to_string()),
     description: "Search the web for up-to-date technical information like latest releases, security advisories, migration guides, benchmarks, and community insights".to_string(),
     input_schema: json!({
         "type": "object",
@@ -21,10 +22,16 @@ pub static FELO_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| {
                 "type": "boolean",
                 "description": "Whether to stream the response (default: false)",
                 "default": false
+            },
+            "noCache": {
+                "type": "boolean",
+                "description": "Skip cached results and fetch fresh ones (default: false)",
+                "default": false
             }
         },
         "required": ["query"]
     }),
+    output_schema: None,
     annotations: Some(ToolAnnotations {
         title: Some("Felo AI Search".to_string()),
         read_only_hint: Some(true),
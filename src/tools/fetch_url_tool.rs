@@ -1,45 +1,56 @@
-use once_cell::sync::Lazy;
 use serde::Deserialize;
 use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tracing::{error, info, warn};
 
 use crate::mcp::types::{CallToolResult, ToolAnnotations, ToolDefinition};
-use crate::utils::content_guard::{build_error_payload, safe_truncate_utf8};
+use crate::utils::content_guard::safe_truncate_utf8;
+use crate::utils::fetch_error::FetchError;
 use crate::utils::readability_extract::{fetch_url_content, ExtractionKind};
 
-pub static FETCH_URL_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| ToolDefinition {
-    name: "fetch-url".to_string(),
-    description:
-        "Fetch the content of a URL and return it as text, with options to control extraction"
-            .to_string(),
-    input_schema: json!({
-        "type": "object",
-        "properties": {
-            "url": {
-                "type": "string",
-                "description": "The URL to fetch"
-            },
-            "maxLength": {
-                "type": "integer",
-                "description": "Maximum length of content to return (default: 30000)",
-                "default": 30000,
-                "minimum": 1000,
-                "maximum": 500000
-            },
-            "extractMainContent": {
-                "type": "boolean",
-                "description": "Whether to attempt to extract main content (default: true)",
-                "default": true
-            }
-        },
-        "required": ["url"]
-    }),
-    annotations: Some(ToolAnnotations {
+/// Built on each `tools/list` because the default `maxLength` is configurable.
+pub fn fetch_url_tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "fetch-url".to_string(),
         title: Some("Fetch URL Content".to_string()),
-        read_only_hint: Some(true),
-        open_world_hint: Some(true),
-    }),
-});
+        description:
+            "Fetch the content of a URL and return it as text, with options to control extraction"
+                .to_string(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "url": {
+                    "type": "string",
+                    "description": "The URL to fetch"
+                },
+                "maxLength": {
+                    "type": "integer",
+                    "description": format!("Maximum length of content to return (default: {})", default_max_length()),
+                    "default": default_max_length(),
+                    "minimum": MAX_LENGTH_RANGE.0,
+                    "maximum": MAX_LENGTH_RANGE.1
+                },
+                "extractMainContent": {
+                    "type": "boolean",
+                    "description": "Whether to attempt to extract main content (default: true)",
+                    "default": true
+                },
+                "noCache": {
+                    "type": "boolean",
+                    "description": "Skip cached results and fetch fresh ones (default: false)",
+                    "default": false
+                }
+            },
+            "required": ["url"]
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations {
+            title: Some("Fetch URL Content".to_string()),
+            read_only_hint: Some(true),
+            open_world_hint: Some(true),
+        }),
+    }
+}
 
 #[derive(Debug, Deserialize)]
 struct FetchUrlParams {
@@ -50,8 +61,21 @@ struct FetchUrlParams {
     extract_main_content: bool,
 }
 
+/// Smallest and largest `maxLength` accepted by the schema
+pub const MAX_LENGTH_RANGE: (usize, usize) = (1000, 500000);
+
+/// `maxLength` used when neither the call nor the configuration sets one
+pub const BUILTIN_MAX_LENGTH: usize = 30000;
+
+static DEFAULT_MAX_LENGTH: AtomicUsize = AtomicUsize::new(BUILTIN_MAX_LENGTH);
+
+/// Sets the `maxLength` used when a call does not pass one.
+pub fn set_default_max_length(max_length: usize) {
+    DEFAULT_MAX_LENGTH.store(max_length, Ordering::Relaxed);
+}
+
 fn default_max_length() -> usize {
-    30000
+    DEFAULT_MAX_LENGTH.load(Ordering::Relaxed)
 }
 
 fn default_true() -> bool {
@@ -131,52 +155,17 @@ impl FetchUrlTool {
                 CallToolResult::success(format!("{}{}", truncated_content, metadata))
             }
             Err(e) => {
-                let err_str = e.to_string();
-                // Detect standardized payloads by inspecting the trailing JSON line starting with '{'
-                let mut code: Option<String> = None;
-                if let Some(json_line) = err_str
-                    .lines()
-                    .rev()
-                    .find(|l| l.trim_start().starts_with('{'))
-                {
-                    if let Ok(v) = serde_json::from_str::<serde_json::Value>(json_line) {
-                        code = v
-                            .get("code")
-                            .and_then(|c| c.as_str())
-                            .map(|s| s.to_string());
-                    }
-                }
-
-                match code.as_deref() {
-                    Some("ERR_FETCH_UNSUPPORTED_BINARY") => {
-                        warn!("Refused fetch due to unsupported binary content (policy=binary-guard) for URL {}", params.url);
-                        CallToolResult::error(err_str)
-                    }
-                    Some("ERR_FETCH_HTTP")
-                    | Some("ERR_FETCH_PDF_PARSE")
-                    | Some("ERR_FETCH_PDF_ENCRYPTED") => {
-                        // Pass-through standardized HTTP/PDF errors as-is
-                        warn!(
-                            "Standardized fetch error for URL {}: {}",
-                            params.url,
-                            code.unwrap()
-                        );
-                        CallToolResult::error(err_str)
-                    }
-                    _ => {
-                        // Unknown error — do not leak internal traces in external message
-                        error!("Error fetching URL {}: {}", params.url, err_str);
-                        let payload = build_error_payload(
-                            "ERR_FETCH_UNKNOWN",
-                            "An unknown error occurred while fetching the content",
-                            json!({
-                                "url": params.url,
-                                "hint": "Please try again later or provide a different URL."
-                            }),
-                        );
-                        CallToolResult::error(payload)
+                match e {
+                    FetchError::UnsupportedBinary { .. } => {
+                        warn!("Refused fetch due to unsupported binary content (policy=binary-guard) for URL {}", params.url)
                     }
+                    _ => warn!(
+                        "Standardized fetch error for URL {}: {}",
+                        params.url,
+                        e.code()
+                    ),
                 }
+                e.to_tool_result()
             }
         }
     }
@@ -4,11 +4,16 @@ use serde_json::json;
 use tracing::{error, info};
 
 use crate::mcp::types::{CallToolResult, ToolAnnotations, ToolDefinition};
-use crate::utils::google_search::{GoogleSearchFilters, GoogleSearchService};
+use crate::utils::google_search::{
+    categorize, category_stats, CategoryInfo, GoogleSearchOptions, GoogleSearchService,
+    SearchPaginationInfo,
+};
+use crate::utils::search_provider::{self, SearchRequest, SearchResult, TimeRange};
 
 pub static GOOGLE_SEARCH_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| {
     ToolDefinition {
     name: "google-search".to_string(),
+    title: Some("Google Search".to_string()),
     description: "Search Google and return relevant results from the web. This tool finds web pages, articles, and information on specific topics using Google's search engine. Results include titles, snippets, and URLs that can be analyzed further.".to_string(),
     input_schema: json!({
         "type": "object",
@@ -32,6 +37,10 @@ pub static GOOGLE_SEARCH_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| {
                 "type": "string",
                 "description": "Filter results by language using ISO 639-1 codes (e.g., \"en\" for English, \"es\" for Spanish, \"fr\" for French)."
             },
+            "region": {
+                "type": "string",
+                "description": "Boost results from a country, as an ISO 3166-1 code (e.g., \"us\", \"de\")."
+            },
             "dateRestrict": {
                 "type": "string",
                 "description": "Filter results by date using Google's date restriction format: \"d[number]\" for past days, \"w[number]\" for past weeks, \"m[number]\" for past months, or \"y[number]\" for past years. Example: \"m6\" for results from the past 6 months."
@@ -60,10 +69,62 @@ pub static GOOGLE_SEARCH_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| {
             "sort": {
                 "type": "string",
                 "description": "Sorting method for search results. Options: \"relevance\" (default) or \"date\" (most recent first)."
+            },
+            "noCache": {
+                "type": "boolean",
+                "description": "Skip cached results and fetch fresh ones (default: false)",
+                "default": false
             }
         },
         "required": ["query"]
     }),
+    output_schema: Some(json!({
+        "type": "object",
+        "properties": {
+            "query": { "type": "string" },
+            "results": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "title": { "type": "string" },
+                        "url": { "type": "string" },
+                        "snippet": { "type": "string" },
+                        "display_url": { "type": "string" },
+                        "date_published": { "type": "string" },
+                        "category": { "type": ["string", "null"] }
+                    },
+                    "required": ["title", "url", "snippet", "display_url"]
+                }
+            },
+            "pagination": {
+                "type": ["object", "null"],
+                "properties": {
+                    "current_page": { "type": "integer" },
+                    "total_results": { "type": ["integer", "null"] },
+                    "results_per_page": { "type": "integer" },
+                    "total_pages": { "type": ["integer", "null"] },
+                    "has_next_page": { "type": "boolean" },
+                    "has_previous_page": { "type": "boolean" }
+                }
+            },
+            "categories": {
+                "type": ["array", "null"],
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "count": { "type": "integer" }
+                    }
+                }
+            },
+            "filtered": {
+                "type": "integer",
+                "description": "Results removed by the server's domain policy"
+            }
+        },
+        "required": ["query", "results", "filtered"]
+    })),
     annotations: Some(ToolAnnotations {
         title: Some("Google Search".to_string()),
         read_only_hint: Some(true),
@@ -79,6 +140,7 @@ struct GoogleSearchParams {
     num_results: u32,
     site: Option<String>,
     language: Option<String>,
+    region: Option<String>,
     #[serde(rename = "dateRestrict")]
     date_restrict: Option<String>,
     #[serde(rename = "exactTerms")]
@@ -144,99 +206,124 @@ impl GoogleSearchTool {
         };
 
         // Validate parameters
-        if params.num_results > 10 {
-            return CallToolResult::error("num_results cannot exceed 10".to_string());
+        if !(1..=10).contains(&params.num_results) {
+            return CallToolResult::error("num_results must be between 1 and 10".to_string());
+        }
+
+        if !(1..=10).contains(&params.results_per_page) {
+            return CallToolResult::error("resultsPerPage must be between 1 and 10".to_string());
         }
 
-        if params.results_per_page > 10 {
-            return CallToolResult::error("resultsPerPage cannot exceed 10".to_string());
+        if params.page == 0 {
+            return CallToolResult::error("page must be at least 1".to_string());
         }
 
+        let time_range = match params.date_restrict.as_deref().map(str::parse::<TimeRange>) {
+            Some(Err(e)) => return CallToolResult::error(format!("Invalid parameters: {}", e)),
+            Some(Ok(range)) => Some(range),
+            None => None,
+        };
+
         info!(
             "Performing Google search for: {} (page {}, {} results)",
             params.query, params.page, params.num_results
         );
 
-        let filters = GoogleSearchFilters {
-            site: params.site,
+        // Pages are resultsPerPage long; numResults caps what is returned of one
+        let request = SearchRequest {
             language: params.language,
-            date_restrict: params.date_restrict,
+            region: params.region,
+            time_range,
+            site: params.site,
+            ..SearchRequest::new(params.query, params.page, params.results_per_page)
+        };
+        let provider = service.with_options(GoogleSearchOptions {
             exact_terms: params.exact_terms,
             result_type: params.result_type,
-            page: Some(params.page),
-            results_per_page: Some(params.results_per_page),
             sort: params.sort,
-        };
+        });
+
+        match search_provider::search(&provider, &request).await {
+            Ok(outcome) => {
+                let mut results = outcome.page.results;
+                results.truncate(params.num_results as usize);
+                let filtered = outcome.filtered;
+                let pagination = SearchPaginationInfo::new(&request, outcome.page.total_results);
+                let categories = category_stats(&results);
 
-        match service
-            .search(&params.query, Some(params.num_results), Some(filters))
-            .await
-        {
-            Ok(response) => {
-                info!("Found {} results", response.results.len());
-
-                if response.results.is_empty() {
-                    return CallToolResult::success(
-                        "No results found. Try:\n- Using different keywords\n- Removing quotes from non-exact phrases\n- Using more general terms".to_string()
-                    );
+                let structured = Self::structured_output(
+                    &request.query,
+                    &results,
+                    &pagination,
+                    &categories,
+                    filtered,
+                );
+
+                if results.is_empty() {
+                    let text = "No results found. Try:\n- Using different keywords\n- Removing quotes from non-exact phrases\n- Using more general terms".to_string()
+                        + &search_provider::filtered_note(filtered);
+                    return CallToolResult::success(text).with_structured_content(structured);
                 }
 
                 // Format results in a more AI-friendly way
-                let mut response_text = format!("Search results for \"{}\":\n\n", params.query);
-
-                // Add category summary if available
-                if let Some(ref categories) = response.categories {
-                    if !categories.is_empty() {
-                        let category_summary: Vec<String> = categories
-                            .iter()
-                            .map(|c| format!("{} ({})", c.name, c.count))
-                            .collect();
-                        response_text
-                            .push_str(&format!("Categories: {}\n\n", category_summary.join(", ")));
-                    }
-                }
+                let mut response_text = format!("Search results for \"{}\":\n\n", request.query);
+
+                // Add category summary
+                let category_summary: Vec<String> = categories
+                    .iter()
+                    .map(|c| format!("{} ({})", c.name, c.count))
+                    .collect();
+                response_text.push_str(&format!("Categories: {}\n\n", category_summary.join(", ")));
 
                 // Add pagination info
-                if let Some(ref pagination) = response.pagination {
-                    if let Some(total_results) = pagination.total_results {
-                        response_text.push_str(&format!(
-                            "Showing page {} of approximately {} results\n\n",
-                            pagination.current_page, total_results
-                        ));
-                    } else {
-                        response_text
-                            .push_str(&format!("Showing page {}\n\n", pagination.current_page));
-                    }
+                if let Some(total_results) = pagination.total_results {
+                    response_text.push_str(&format!(
+                        "Showing page {} of approximately {} results\n\n",
+                        pagination.current_page, total_results
+                    ));
+                } else {
+                    response_text
+                        .push_str(&format!("Showing page {}\n\n", pagination.current_page));
+                }
+
+                if filtered > 0 {
+                    response_text.push_str(&format!(
+                        "{} results hidden by the domain policy\n\n",
+                        filtered
+                    ));
                 }
 
                 // Add each result in a readable format
-                for (index, result) in response.results.iter().enumerate() {
+                for (index, result) in results.iter().enumerate() {
                     response_text.push_str(&format!("{}. {}\n", index + 1, result.title));
-                    response_text.push_str(&format!("   URL: {}\n", result.link));
+                    response_text.push_str(&format!("   URL: {}\n", result.url));
                     response_text.push_str(&format!("   {}\n\n", result.snippet));
                 }
 
-                // Add navigation hints if pagination exists
-                if let Some(ref pagination) = response.pagination {
-                    if pagination.has_next_page || pagination.has_previous_page {
-                        response_text.push_str("Navigation: ");
-                        if pagination.has_previous_page {
-                            response_text.push_str(&format!(
-                                "Use 'page: {}' for previous results. ",
-                                pagination.current_page - 1
-                            ));
-                        }
-                        if pagination.has_next_page {
-                            response_text.push_str(&format!(
-                                "Use 'page: {}' for more results.",
-                                pagination.current_page + 1
-                            ));
-                        }
-                        response_text.push('\n');
+                // Add navigation hints
+                if pagination.has_next_page || pagination.has_previous_page {
+                    response_text.push_str("Navigation: ");
+                    if pagination.has_previous_page {
+                        response_text.push_str(&format!(
+                            "Use 'page: {}' for previous results. ",
+                            pagination.current_page - 1
+                        ));
+                    }
+                    if pagination.has_next_page {
+                        response_text.push_str(&format!(
+                            "Use 'page: {}' for more results.",
+                            pagination.current_page + 1
+                        ));
                     }
+                    response_text.push('\n');
                 }
 
-                CallToolResult::success(response_text)
+                let mut result =
+                    CallToolResult::success(response_text).with_structured_content(structured);
+                result
+                    .content
+                    .extend(search_provider::resource_links(&results));
+                result
             }
             Err(e) => {
                 error!("Google search error: {}", e);
@@ -244,4 +331,35 @@ impl GoogleSearchTool {
             }
         }
     }
+
+    // Typed counterpart of the text rendering
+    fn structured_output(
+        query: &str,
+        results: &[SearchResult],
+        pagination: &SearchPaginationInfo,
+        categories: &[CategoryInfo],
+        filtered: usize,
+    ) -> serde_json::Value {
+        let results: Vec<serde_json::Value> = results
+            .iter()
+            .map(|r| {
+                json!({
+                    "title": r.title,
+                    "url": r.url,
+                    "snippet": r.snippet,
+                    "display_url": r.display_url,
+                    "date_published": r.date_published.clone().unwrap_or_default(),
+                    "category": categorize(r),
+                })
+            })
+            .collect();
+
+        json!({
+            "query": query,
+            "results": results,
+            "pagination": pagination,
+            "categories": categories,
+            "filtered": filtered,
+        })
+    }
 }
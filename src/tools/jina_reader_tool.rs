@@ -1,15 +1,20 @@
-use once_cell::sync::Lazy;
 use serde::Deserialize;
 use serde_json::json;
-use tracing::{error, info};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{error, info, warn};
 
 use crate::mcp::types::{CallToolResult, ToolAnnotations, ToolDefinition};
+use crate::utils::address_guard;
 use crate::utils::content_guard::safe_truncate_utf8;
-use crate::utils::jina_reader::{JinaReaderParams as ServiceParams, JinaReaderService}; // use unified safe truncation
+use crate::utils::jina_reader::{
+    JinaReaderError, JinaReaderParams as ServiceParams, JinaReaderService,
+}; // use unified safe truncation
 
-pub static JINA_READER_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| {
+/// Built on each `tools/list` because the default `maxLength` is configurable.
+pub fn jina_reader_tool_definition() -> ToolDefinition {
     ToolDefinition {
     name: "jina-reader".to_string(),
+    title: Some("Jina Reader".to_string()),
     description: "Retrieve LLM-friendly content from a single website URL using Jina r.reader API. Useful when you know the specific source of information.".to_string(),
     input_schema: json!({
         "type": "object",
@@ -20,10 +25,10 @@ pub static JINA_READER_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| {
             },
             "maxLength": {
                 "type": "integer",
-                "description": "Maximum length of content to return (default: 10000)",
-                "default": 10000,
-                "minimum": 1000,
-                "maximum": 50000
+                "description": format!("Maximum length of content to return (default: {})", default_max_length()),
+                "default": default_max_length(),
+                "minimum": MAX_LENGTH_RANGE.0,
+                "maximum": MAX_LENGTH_RANGE.1
             },
             "withLinksummary": {
                 "type": "boolean",
@@ -48,7 +53,7 @@ pub static JINA_READER_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| {
             },
             "noCache": {
                 "type": "boolean",
-                "description": "Bypass cache for fresh retrieval (default: false)",
+                "description": "Bypass Jina's and this server's caches for fresh retrieval (default: false)",
                 "default": false
             },
             "timeout": {
@@ -61,13 +66,14 @@ pub static JINA_READER_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| {
         },
         "required": ["url"]
     }),
+    output_schema: None,
     annotations: Some(ToolAnnotations {
         title: Some("Jina Reader".to_string()),
         read_only_hint: Some(true),
         open_world_hint: Some(true),
     }),
+    }
 }
-});
 
 #[derive(Debug, Deserialize)]
 struct JinaReaderParams {
@@ -88,8 +94,21 @@ struct JinaReaderParams {
     timeout: u32,
 }
 
+/// Smallest and largest `maxLength` accepted by the schema
+pub const MAX_LENGTH_RANGE: (usize, usize) = (1000, 50000);
+
+/// `maxLength` used when neither the call nor the configuration sets one
+pub const BUILTIN_MAX_LENGTH: usize = 10000;
+
+static DEFAULT_MAX_LENGTH: AtomicUsize = AtomicUsize::new(BUILTIN_MAX_LENGTH);
+
+/// Sets the `maxLength` used when a call does not pass one.
+pub fn set_default_max_length(max_length: usize) {
+    DEFAULT_MAX_LENGTH.store(max_length, Ordering::Relaxed);
+}
+
 fn default_max_length() -> usize {
-    10000
+    DEFAULT_MAX_LENGTH.load(Ordering::Relaxed)
 }
 
 fn default_false() -> bool {
@@ -139,8 +158,18 @@ impl JinaReaderTool {
         };
 
         // Validate URL
-        if let Err(e) = url::Url::parse(&params.url) {
-            return CallToolResult::error(format!("Invalid URL: {}", e));
+        let parsed_url = match url::Url::parse(&params.url) {
+            Ok(url) => url,
+            Err(e) => return CallToolResult::error(format!("Invalid URL: {}", e)),
+        };
+        // Jina fetches on its side, but internal URLs must not leave this network either
+        if let Err(e) = address_guard::check_target(&parsed_url) {
+            warn!(
+                "Refused Jina Reader request for {}: {}",
+                params.url,
+                e.code()
+            );
+            return e.to_tool_result();
         }
 
         info!(
@@ -221,6 +250,14 @@ impl JinaReaderTool {
                 result.push_str(&metadata);
                 CallToolResult::success(result)
             }
+            Err(JinaReaderError::Refused(e)) => {
+                warn!(
+                    "Refused Jina Reader request for {}: {}",
+                    params.url,
+                    e.code()
+                );
+                e.to_tool_result()
+            }
             Err(e) => {
                 error!("Error reading URL with Jina Reader {}: {}", params.url, e);
                 CallToolResult::error(format!("Error reading URL: {}", e))
@@ -1,13 +1,16 @@
 use once_cell::sync::Lazy;
 use serde::Deserialize;
 use serde_json::json;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::mcp::types::{CallToolResult, ToolAnnotations, ToolDefinition};
+use crate::utils::address_guard;
 use crate::utils::duckduckgo_search::extract_url_metadata;
+use crate::utils::fetch_error::FetchError;
 
 pub static METADATA_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| ToolDefinition {
     name: "url-metadata".to_string(),
+    title: Some("URL Metadata".to_string()),
     description: "Extract metadata from a URL (title, description, etc.)".to_string(),
     input_schema: json!({
         "type": "object",
@@ -15,10 +18,16 @@ pub static METADATA_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| ToolDef
             "url": {
                 "type": "string",
                 "description": "The URL to extract metadata from"
+            },
+            "noCache": {
+                "type": "boolean",
+                "description": "Skip cached results and fetch fresh ones (default: false)",
+                "default": false
             }
         },
         "required": ["url"]
     }),
+    output_schema: None,
     annotations: Some(ToolAnnotations {
         title: Some("URL Metadata".to_string()),
         read_only_hint: Some(true),
@@ -53,8 +62,13 @@ impl MetadataTool {
         };
 
         // Validate URL
-        if let Err(e) = url::Url::parse(&params.url) {
-            return CallToolResult::error(format!("Invalid URL: {}", e));
+        let parsed_url = match url::Url::parse(&params.url) {
+            Ok(url) => url,
+            Err(e) => return CallToolResult::error(format!("Invalid URL: {}", e)),
+        };
+        if let Err(e) = address_guard::check_fetch_target(&parsed_url).await {
+            warn!("Refused metadata request for {}: {}", params.url, e.code());
+            return e.to_tool_result();
         }
 
         info!("Extracting metadata from URL: {}", params.url);
@@ -74,6 +88,15 @@ impl MetadataTool {
                 CallToolResult::success(formatted_metadata)
             }
             Err(e) => {
+                if let Some(e) = e.downcast_ref::<FetchError>() {
+                    warn!("Refused metadata request for {}: {}", params.url, e.code());
+                    return e.to_tool_result();
+                }
+                // Internal targets reached through DNS or a redirect get the dedicated error
+                if let Some(blocked) = e.chain().find_map(|err| address_guard::find_blocked(err)) {
+                    warn!("Refused metadata request for {}: {}", params.url, blocked);
+                    return FetchError::from_blocked(&params.url, blocked).to_tool_result();
+                }
                 error!("Error extracting metadata from {}: {}", params.url, e);
                 CallToolResult::error(format!("Error extracting metadata: {}", e))
             }
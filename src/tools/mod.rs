@@ -5,3 +5,14 @@ pub mod google_search_tool;
 pub mod jina_reader_tool;
 pub mod metadata_tool;
 pub mod url_fetch_tool;
+
+/// Names of all tools, including those only listed when their backend is configured
+pub const TOOL_NAMES: [&str; 7] = [
+    "duckduckgo-search",
+    "google-search",
+    "felo-search",
+    "fetch-url",
+    "url-metadata",
+    "url-fetch",
+    "jina-reader",
+];
@@ -16,13 +16,25 @@ use std::sync::OnceLock;
 use std::time::Instant;
 use tracing::{error, info, warn};
 
-use crate::utils::content_guard::{build_error_payload, detect_binary, BinaryDetection};
-use crate::utils::pdf::{extract_text_from_pdf_mem, is_pdf};
+use crate::utils::address_guard;
+use crate::utils::body_limit;
+use crate::utils::content_guard::{detect_binary, BinaryDetection};
+use crate::utils::domain_policy;
+use crate::utils::fetch_error::FetchError;
+use crate::utils::http::{self, Backend};
+use crate::utils::page_store::store_page;
+use crate::utils::pdf::{extract_text_from_pdf_blocking, is_pdf};
+use crate::utils::progress::report_message;
+use crate::utils::rate_limit;
+use crate::utils::retry;
+use crate::utils::revalidate::{self, Download, Validators};
+use crate::utils::robots;
 
 use crate::mcp::types::{CallToolResult, ToolAnnotations, ToolDefinition};
 
 pub static URL_FETCH_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| ToolDefinition {
     name: "url-fetch".to_string(),
+    title: Some("URL Fetch Tool".to_string()),
     description: "Fetch web pages and convert them to markdown format".to_string(),
     input_schema: json!({
         "type": "object",
@@ -30,10 +42,16 @@ pub static URL_FETCH_TOOL_DEFINITION: Lazy<ToolDefinition> = Lazy::new(|| ToolDe
             "url": {
                 "type": "string",
                 "description": "The URL to fetch and convert to markdown"
+            },
+            "noCache": {
+                "type": "boolean",
+                "description": "Skip cached results and fetch fresh ones (default: false)",
+                "default": false
             }
         },
         "required": ["url"]
     }),
+    output_schema: None,
     annotations: Some(ToolAnnotations {
         title: Some("URL Fetch Tool".to_string()),
         read_only_hint: Some(true),
@@ -652,11 +670,7 @@ impl HandleTag for CodeHandler {
         writer: &mut MarkdownWriter,
     ) -> StartTagOutcome {
         match tag.tag() {
-            "code" => {
-                if !writer.is_inside("pre") {
-                    writer.push_str("`");
-                }
-            }
+            "code" if !writer.is_inside("pre") => writer.push_str("`"),
             "pre" => writer.push_str("\n\n```\n"),
             _ => {}
         }
@@ -666,11 +680,7 @@ impl HandleTag for CodeHandler {
 
     fn handle_tag_end(&mut self, tag: &HtmlElement, writer: &mut MarkdownWriter) {
         match tag.tag() {
-            "code" => {
-                if !writer.is_inside("pre") {
-                    writer.push_str("`");
-                }
-            }
+            "code" if !writer.is_inside("pre") => writer.push_str("`"),
             "pre" => writer.push_str("\n```\n"),
             _ => {}
         }
@@ -724,116 +734,82 @@ impl UrlFetchTool {
         Self
     }
 
-    async fn build_message(url: &str) -> anyhow::Result<String> {
+    /// Downloads `url` (conditionally, when `validators` are given) and converts it to markdown.
+    async fn fetch_page(
+        url: &str,
+        validators: Option<Validators>,
+    ) -> Result<Download<String>, FetchError> {
         let url = if !url.starts_with("https://") && !url.starts_with("http://") {
             format!("https://{}", url)
         } else {
             url.to_string()
         };
 
-        let client = reqwest::Client::new();
-        let response = client
-            .get(&url)
-            .send()
+        if let Ok(parsed) = url::Url::parse(&url) {
+            if let Some(host) = parsed.host_str() {
+                rate_limit::acquire_host(host)
+                    .await
+                    .map_err(|e| FetchError::from_rate_limited(&url, &e))?;
+            }
+            robots::wait_for_crawl_delay(&parsed, &http::user_agent()).await;
+        }
+        let mut request = http::client_for(Backend::Fetch).get(&url);
+        if let Some(validators) = &validators {
+            request = validators.apply(request);
+        }
+        let response = retry::send(Backend::Fetch, request)
             .await
-            .map_err(|e| anyhow::anyhow!("Failed to fetch URL: {}", e))?;
+            .map_err(|e| FetchError::from_reqwest(&url, &e))?;
+        // Redirects may have left the allowed domains or the pages robots.txt permits
+        domain_policy::check_url(response.url())?;
+        if let Ok(requested) = url::Url::parse(&url) {
+            robots::check_redirected(&requested, response.url(), &http::user_agent()).await?;
+        }
 
+        if let Some(not_modified) = Download::not_modified(validators.as_ref(), &response) {
+            return Ok(not_modified);
+        }
         if !response.status().is_success() {
-            // Map real HTTP errors to standardized MCP payload with explicit code
-            let status = response.status();
-            let code_num = status.as_u16();
-            let reason = status.canonical_reason().unwrap_or("Unknown error");
-            let msg = format!("HTTP error {}: {}", code_num, reason);
-            let payload = build_error_payload(
-                "ERR_FETCH_HTTP",
-                &msg,
-                serde_json::json!({
-                    "url": url,
-                    "httpStatus": code_num,
-                    "reason": reason,
-                    "hint": if code_num == 404 { "The resource was not found (404)." } else { "Please verify the URL and try again." }
-                }),
-            );
-            return Err(anyhow::anyhow!(format!("__TOOL_ERROR__{}", payload)));
-        }
-        // Capture header and status for later use
-        let status_code = response.status().as_u16();
+            return Err(FetchError::from_status(&url, response.status()));
+        }
+        let validators = Validators::from_response(&response);
+        let markdown = Self::build_message(url, response).await?;
+        Ok(Download::Modified(markdown, validators))
+    }
+
+    async fn build_message(url: String, response: reqwest::Response) -> Result<String, FetchError> {
+        // Capture header for later use
         let content_type_opt: Option<String> = response
             .headers()
             .get("content-type")
             .and_then(|ct| ct.to_str().ok())
             .map(|s| s.to_string());
 
-        // Early size guard for PDFs indicated by header-only (avoid reading body if too large)
-        const PDF_LIMIT_BYTES: u64 = 500 * 1024 * 1024; // 500 MiB
-        let content_length_opt = response.content_length();
-        let header_indicates_pdf = content_type_opt
-            .as_deref()
-            .map(|ct| ct.to_ascii_lowercase().contains("application/pdf"))
-            .unwrap_or(false);
-
-        if header_indicates_pdf {
-            if let Some(clen) = content_length_opt {
-                if clen > PDF_LIMIT_BYTES {
+        // Read the body within the size cap for its content type; we peek into the first 512 bytes.
+        let body = body_limit::read_body(&url, content_type_opt.as_deref(), response)
+            .await
+            .map_err(|e| {
+                if let FetchError::TooLarge {
+                    limit, bytes_read, ..
+                } = &e
+                {
                     warn!(
-                        "Refusing oversized PDF by header before body download url={} content_length={} limit={}",
-                        url,
-                        clen,
-                        PDF_LIMIT_BYTES
+                        "Refusing oversized body url={} bytes_read={} limit={}",
+                        url, bytes_read, limit
                     );
-                    let details = serde_json::json!({
-                        "url": url,
-                        "httpStatus": status_code,
-                        "contentType": content_type_opt,
-                        "size": clen,
-                        "limit": PDF_LIMIT_BYTES,
-                        "hint": "PDF exceeds 500 MiB limit and will not be processed.",
-                    });
-                    let payload = build_error_payload(
-                        "ERR_FETCH_PDF_PARSE",
-                        "PDF exceeds the allowed size limit",
-                        details,
-                    );
-                    return Err(anyhow::anyhow!(format!("__TOOL_ERROR__{}", payload)));
                 }
-            }
-        }
-
-        // Read the body once (simple strategy). We will peek into the first 512 bytes.
-        let body = response
-            .bytes()
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to read response body: {}", e))?;
+                e
+            })?;
 
         // PDF path: detect before the generic binary guard to avoid false refusal
         let head_len = std::cmp::min(512, body.len());
         let head = &body[..head_len];
         if is_pdf(content_type_opt.as_deref(), head) {
             let size = body.len() as u64;
-            if size > PDF_LIMIT_BYTES {
-                warn!(
-                    "Refusing oversized PDF after download url={} size_bytes={} limit={}",
-                    url, size, PDF_LIMIT_BYTES
-                );
-                let details = serde_json::json!({
-                    "url": url,
-                    "httpStatus": status_code,
-                    "contentType": content_type_opt,
-                    "size": size,
-                    "limit": PDF_LIMIT_BYTES,
-                    "hint": "PDF exceeds 500 MiB limit and will not be processed.",
-                });
-                let payload = build_error_payload(
-                    "ERR_FETCH_PDF_PARSE",
-                    "PDF exceeds the allowed size limit",
-                    details,
-                );
-                return Err(anyhow::anyhow!(format!("__TOOL_ERROR__{}", payload)));
-            }
-
             info!("Starting PDF extraction url={} size_bytes={}", url, size);
+            report_message(format!("Extracting PDF text ({} bytes)", size));
             let started = Instant::now();
-            match extract_text_from_pdf_mem(&body) {
+            match extract_text_from_pdf_blocking(body).await {
                 Ok(text) => {
                     let took_ms = started.elapsed().as_millis();
                     info!(
@@ -849,19 +825,12 @@ impl UrlFetchTool {
                         "PDF extraction failed url={} size_bytes={} error={}",
                         url, size, err
                     );
-                    let details = serde_json::json!({
-                        "url": url,
-                        "httpStatus": status_code,
-                        "contentType": content_type_opt,
-                        "size": size,
-                        "hint": "Failed to parse PDF. The file may be corrupted or encrypted.",
-                    });
-                    let payload = build_error_payload(
-                        "ERR_FETCH_PDF_PARSE",
-                        "Failed to parse the PDF document",
-                        details,
-                    );
-                    return Err(anyhow::anyhow!(format!("__TOOL_ERROR__{}", payload)));
+                    return Err(FetchError::from_pdf_error(
+                        &url,
+                        content_type_opt,
+                        size,
+                        &err.to_string(),
+                    ));
                 }
             }
         }
@@ -869,27 +838,18 @@ impl UrlFetchTool {
         // Binary guard before mapping ContentType
         match detect_binary(content_type_opt.as_deref(), head) {
             BinaryDetection::Binary { content_type } => {
-                // Build standardized error payload and wrap with recognizable prefix for pass-through
-                let ct_eff = content_type.clone().or_else(|| content_type_opt.clone());
+                let ct_eff = content_type.or_else(|| content_type_opt.clone());
                 warn!(
                     "Refusing binary content (policy=binary-guard) url={} content_type={:?} size_bytes={}",
                     url,
                     ct_eff,
                     body.len()
                 );
-                let details = serde_json::json!({
-                    "url": url,
-                    "httpStatus": status_code,
-                    "contentType": ct_eff,
-                    "size": body.len(),
-                    "hint": "Binary content is not supported by this tool. Provide a text-based URL or use a tool that handles binary files."
+                return Err(FetchError::UnsupportedBinary {
+                    url,
+                    content_type: ct_eff,
+                    size: Some(body.len() as u64),
                 });
-                let payload = build_error_payload(
-                    "ERR_FETCH_UNSUPPORTED_BINARY",
-                    "Fetch cannot be performed for this type of content",
-                    details,
-                );
-                return Err(anyhow::anyhow!(format!("__TOOL_ERROR__{}", payload)));
             }
             BinaryDetection::Text => {}
         }
@@ -903,6 +863,12 @@ impl UrlFetchTool {
             _ => ContentType::Html,
         };
 
+        let decode_error = |message: &str, detail: String| FetchError::Decode {
+            url: url.clone(),
+            message: message.to_string(),
+            detail,
+        };
+
         match content_type {
             ContentType::Html => {
                 let mut handlers: Vec<TagHandler> = vec![
@@ -917,24 +883,67 @@ impl UrlFetchTool {
                     Rc::new(RefCell::new(CodeHandler)),
                 ];
 
-                convert_html_to_markdown(&body, &mut handlers)
+                convert_html_to_markdown(&body, &mut handlers).map_err(|e| {
+                    decode_error("Failed to convert HTML content to markdown", e.to_string())
+                })
             }
             ContentType::Plaintext => Ok(std::str::from_utf8(&body)
-                .map_err(|e| anyhow::anyhow!("Invalid UTF-8: {}", e))?
+                .map_err(|e| decode_error("Invalid UTF-8 in text content", e.to_string()))?
                 .to_owned()),
             ContentType::Json => {
                 let json: serde_json::Value = serde_json::from_slice(&body)
-                    .map_err(|e| anyhow::anyhow!("Invalid JSON: {}", e))?;
+                    .map_err(|e| decode_error("Invalid JSON content", e.to_string()))?;
 
                 Ok(format!(
                     "```json\n{}\n```",
                     serde_json::to_string_pretty(&json)
-                        .map_err(|e| anyhow::anyhow!("Failed to format JSON: {}", e))?
+                        .map_err(|e| decode_error("Failed to format JSON", e.to_string()))?
                 ))
             }
         }
     }
 
+    /// Fetches `url` as markdown and remembers non-empty pages in the page store so they
+    /// can be read back as `web://` resources.
+    pub async fn fetch_markdown(url: &str) -> Result<String, FetchError> {
+        if let Ok(parsed) = url::Url::parse(url) {
+            address_guard::check_fetch_target(&parsed).await?;
+            domain_policy::check_url(&parsed)?;
+            robots::check(&parsed, &http::user_agent()).await?;
+        }
+
+        let cache_key = format!("markdown:{}", url);
+        let fetched = revalidate::cached(&cache_key, |validators| {
+            info!("Fetching and converting URL to markdown: {}", url);
+            Self::fetch_page(url, validators)
+        })
+        .await;
+
+        match fetched {
+            Ok(content) => {
+                if !content.trim().is_empty() {
+                    store_page(url, &content);
+                }
+                Ok(content)
+            }
+            Err(e) => {
+                match e {
+                    FetchError::UnsupportedBinary { .. } => {
+                        warn!("Refused binary content for URL {}", url)
+                    }
+                    FetchError::Http { .. } | FetchError::Timeout { .. } => {
+                        warn!("HTTP error while fetching URL {}", url)
+                    }
+                    FetchError::PdfParse { .. } | FetchError::PdfEncrypted { .. } => {
+                        warn!("PDF handling error for URL {}", url)
+                    }
+                    _ => warn!("Fetch error for URL {}: {}", url, e.code()),
+                }
+                Err(e)
+            }
+        }
+    }
+
     pub async fn execute(&self, arguments: Option<serde_json::Value>) -> CallToolResult {
         let params = match arguments {
             Some(args) => match serde_json::from_value::<UrlFetchParams>(args) {
@@ -954,58 +963,12 @@ impl UrlFetchTool {
             return CallToolResult::error(format!("Invalid URL: {}", e));
         }
 
-        info!("Fetching and converting URL to markdown: {}", params.url);
-
-        match Self::build_message(&params.url).await {
-            Ok(content) => {
-                if content.trim().is_empty() {
-                    CallToolResult::error("No textual content found")
-                } else {
-                    CallToolResult::success(content)
-                }
-            }
-            Err(e) => {
-                let msg = e.to_string();
-                if let Some(rest) = msg.strip_prefix("__TOOL_ERROR__") {
-                    // Pass through standardized tool error payload (HTTP/PDF/Binary/etc.)
-                    let mut code: Option<String> = None;
-                    if let Some(json_line) =
-                        rest.lines().rev().find(|l| l.trim_start().starts_with('{'))
-                    {
-                        if let Ok(v) = serde_json::from_str::<serde_json::Value>(json_line) {
-                            code = v
-                                .get("code")
-                                .and_then(|c| c.as_str())
-                                .map(|s| s.to_string());
-                        }
-                    }
-                    match code.as_deref() {
-                        Some("ERR_FETCH_UNSUPPORTED_BINARY") => {
-                            warn!("Refused binary content for URL {}", params.url)
-                        }
-                        Some("ERR_FETCH_HTTP") => {
-                            warn!("HTTP error while fetching URL {}", params.url)
-                        }
-                        Some("ERR_FETCH_PDF_PARSE") | Some("ERR_FETCH_PDF_ENCRYPTED") => {
-                            warn!("PDF handling error for URL {}", params.url)
-                        }
-                        _ => warn!("Standardized tool error for URL {}", params.url),
-                    }
-                    CallToolResult::error(rest.to_string())
-                } else {
-                    // Unknown error: do not expose internal traces
-                    error!("Error fetching URL {}: {}", params.url, msg);
-                    let payload = build_error_payload(
-                        "ERR_FETCH_UNKNOWN",
-                        "An unknown error occurred while fetching the content",
-                        serde_json::json!({
-                            "url": params.url,
-                            "hint": "Please try again later or provide a different URL.",
-                        }),
-                    );
-                    CallToolResult::error(payload)
-                }
+        match Self::fetch_markdown(&params.url).await {
+            Ok(content) if content.trim().is_empty() => {
+                CallToolResult::error("No textual content found")
             }
+            Ok(content) => CallToolResult::success(content),
+            Err(e) => e.to_tool_result(),
         }
     }
 }
@@ -0,0 +1,428 @@
+// SSRF protection: outbound requests may not reach loopback, private, link-local, CGNAT or
+// otherwise internal addresses unless the operator allowlisted them. Hostnames are checked
+// after DNS resolution inside the client's resolver, so every redirect hop and every
+// re-resolution is covered; literal IPs never reach the resolver and are checked on the URL.
+// A proxy resolves target names itself, so for proxied clients the names are also resolved
+// here before the request and on every redirect hop.
+
+use anyhow::{anyhow, Result};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::runtime::RuntimeFlavor;
+use url::{Host, Url};
+
+use crate::utils::fetch_error::FetchError;
+use crate::utils::http::{self, Backend};
+
+/// A request target refused because it resolves to an internal address.
+#[derive(Error, Debug, Clone)]
+#[error("{host} resolves to {address}, which is not a public address")]
+pub struct BlockedAddress {
+    pub host: String,
+    pub address: IpAddr,
+}
+
+/// Entry of `--allow-private-host`: a hostname, an IP address or a CIDR range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AllowRule {
+    Host(String),
+    Network(IpAddr, u8),
+}
+
+impl FromStr for AllowRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some((addr, prefix)) = s.split_once('/') {
+            let addr: IpAddr = addr
+                .parse()
+                .map_err(|_| anyhow!("Invalid network address in '{}'", s))?;
+            let max = if addr.is_ipv4() { 32 } else { 128 };
+            let prefix = prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| anyhow!("Invalid prefix length in '{}'", s))?;
+            return Ok(AllowRule::Network(addr, prefix));
+        }
+        if let Ok(addr) = s
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            let prefix = if addr.is_ipv4() { 32 } else { 128 };
+            return Ok(AllowRule::Network(addr, prefix));
+        }
+        if s.is_empty() || s.contains(|c: char| c.is_whitespace() || c == ':' || c == '/') {
+            return Err(anyhow!("Invalid host '{}'", s));
+        }
+        Ok(AllowRule::Host(
+            s.trim_end_matches('.').to_ascii_lowercase(),
+        ))
+    }
+}
+
+impl fmt::Display for AllowRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllowRule::Host(host) => f.write_str(host),
+            AllowRule::Network(addr, prefix) => write!(f, "{}/{}", addr, prefix),
+        }
+    }
+}
+
+impl AllowRule {
+    fn matches_ip(&self, ip: IpAddr) -> bool {
+        match (self, ip) {
+            (AllowRule::Network(IpAddr::V4(net), prefix), IpAddr::V4(ip)) => {
+                prefix_matches(&net.octets(), &ip.octets(), *prefix)
+            }
+            (AllowRule::Network(IpAddr::V6(net), prefix), IpAddr::V6(ip)) => {
+                prefix_matches(&net.octets(), &ip.octets(), *prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: &[u8], ip: &[u8], prefix: u8) -> bool {
+    let full = (prefix / 8) as usize;
+    let rest = prefix % 8;
+    if net[..full] != ip[..full] {
+        return false;
+    }
+    rest == 0 || (net[full] ^ ip[full]) >> (8 - rest) == 0
+}
+
+/// Which internal targets are reachable anyway.
+#[derive(Debug, Clone, Default)]
+pub struct AddressPolicy {
+    pub allow: Vec<AllowRule>,
+}
+
+impl AddressPolicy {
+    fn allows_host(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.');
+        self.allow
+            .iter()
+            .any(|rule| matches!(rule, AllowRule::Host(h) if h.eq_ignore_ascii_case(host)))
+    }
+
+    fn allows_ip(&self, ip: IpAddr) -> bool {
+        !is_internal(ip) || self.allow.iter().any(|rule| rule.matches_ip(ip))
+    }
+
+    /// Checks the host as written in the URL. Domain names other than `localhost` are
+    /// left to the resolver, since only it knows which address is actually connected to.
+    fn check_url_host(&self, url: &Url) -> Result<(), BlockedAddress> {
+        let (host, ips) = match url.host() {
+            Some(Host::Ipv4(ip)) => (ip.to_string(), vec![IpAddr::V4(ip)]),
+            Some(Host::Ipv6(ip)) => (ip.to_string(), vec![IpAddr::V6(ip)]),
+            Some(Host::Domain(domain)) => {
+                let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+                if domain != "localhost" && !domain.ends_with(".localhost") {
+                    return Ok(());
+                }
+                let ips = vec![
+                    IpAddr::V4(Ipv4Addr::LOCALHOST),
+                    IpAddr::V6(Ipv6Addr::LOCALHOST),
+                ];
+                (domain, ips)
+            }
+            None => return Ok(()),
+        };
+        if self.allows_host(&host) {
+            return Ok(());
+        }
+        self.check_addresses(&host, ips)
+    }
+
+    // Refuses `host` if any of its addresses is internal
+    fn check_addresses(
+        &self,
+        host: &str,
+        ips: impl IntoIterator<Item = IpAddr>,
+    ) -> Result<(), BlockedAddress> {
+        match ips.into_iter().find(|ip| !self.allows_ip(*ip)) {
+            Some(address) => Err(BlockedAddress {
+                host: host.to_string(),
+                address,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks every address the URL's host name resolves to, for requests sent through a
+    /// proxy: the proxy may connect to any of them. Names that do not resolve here are left
+    /// to the proxy.
+    async fn check_resolved(&self, url: &Url) -> Result<(), BlockedAddress> {
+        let Some(Host::Domain(host)) = url.host() else {
+            return Ok(());
+        };
+        if self.allows_host(host) {
+            return Ok(());
+        }
+        match tokio::net::lookup_host((host, 0)).await {
+            Ok(addrs) => self.check_addresses(host, addrs.map(|addr| addr.ip())),
+            Err(_) => Ok(()),
+        }
+    }
+
+    // `check_resolved` for the redirect policy, which cannot await
+    fn check_resolved_blocking(&self, url: &Url) -> Result<(), BlockedAddress> {
+        let Some(Host::Domain(host)) = url.host() else {
+            return Ok(());
+        };
+        if self.allows_host(host) {
+            return Ok(());
+        }
+        let lookup = || (host, 0).to_socket_addrs();
+        let addrs = match tokio::runtime::Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(lookup)
+            }
+            _ => lookup(),
+        };
+        match addrs {
+            Ok(addrs) => self.check_addresses(host, addrs.map(|addr| addr.ip())),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// True for addresses that are not publicly routable: loopback, RFC 1918, link-local
+/// (including cloud metadata endpoints), CGNAT, unique-local, multicast and reserved ranges.
+/// IPv4 addresses embedded in IPv6 (mapped, NAT64) are judged by their IPv4 part.
+pub fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_internal_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_internal_v4(v4);
+            }
+            let segments = ip.segments();
+            // NAT64 well-known prefix 64:ff9b::/96
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b, c, d] = [segments[6] >> 8, segments[6], segments[7] >> 8, segments[7]];
+                return is_internal_v4(Ipv4Addr::new(a as u8, b as u8, c as u8, d as u8));
+            }
+            ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00 // unique local fc00::/7
+                || (segments[0] & 0xffc0) == 0xfe80 // link-local fe80::/10
+                || (segments[0] & 0xffc0) == 0xfec0 // deprecated site-local fec0::/10
+        }
+    }
+}
+
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    ip.is_unspecified()
+        || a == 0 // "this network" 0.0.0.0/8
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || (a == 100 && (64..128).contains(&b)) // CGNAT 100.64.0.0/10
+        || (a == 192 && b == 0 && c == 0) // IETF protocol assignments 192.0.0.0/24
+        || (a == 198 && (b == 18 || b == 19)) // benchmarking 198.18.0.0/15
+        || ip.is_multicast()
+        || a >= 240 // reserved 240.0.0.0/4 and broadcast
+}
+
+/// Refuses non-http(s) URLs and URLs whose literal host (IP address or `localhost`) is
+/// internal. Names that resolve to internal addresses are refused by the client's resolver.
+pub fn check_target(url: &Url) -> Result<(), FetchError> {
+    FetchError::check_scheme(url)?;
+    http::address_policy()
+        .check_url_host(url)
+        .map_err(|blocked| FetchError::from_blocked(url.as_str(), &blocked))
+}
+
+/// `check_target` for page fetches. When they go through a proxy, which resolves the host
+/// name where `GuardedResolver` cannot see it, every address of the name is checked too.
+pub async fn check_fetch_target(url: &Url) -> Result<(), FetchError> {
+    check_target(url)?;
+    if http::is_proxied(Backend::Fetch) {
+        http::address_policy()
+            .check_resolved(url)
+            .await
+            .map_err(|blocked| FetchError::from_blocked(url.as_str(), &blocked))?;
+    }
+    Ok(())
+}
+
+/// DNS resolver that drops internal addresses from every lookup. The hosts of the client's
+/// own proxies are exempt: they are part of the operator's setup, not a target chosen by a
+/// tool caller.
+pub struct GuardedResolver {
+    policy: Arc<AddressPolicy>,
+    exempt_hosts: Vec<String>,
+}
+
+impl GuardedResolver {
+    pub fn new(policy: Arc<AddressPolicy>, exempt_hosts: Vec<String>) -> Self {
+        Self {
+            policy,
+            exempt_hosts,
+        }
+    }
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let policy = self.policy.clone();
+        let exempt = policy.allows_host(&host)
+            || self
+                .exempt_hosts
+                .iter()
+                .any(|h| h.eq_ignore_ascii_case(&host));
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if exempt {
+                return Ok(Box::new(addrs.into_iter()) as Addrs);
+            }
+            let blocked = addrs
+                .iter()
+                .find(|addr| !policy.allows_ip(addr.ip()))
+                .copied();
+            let allowed: Vec<SocketAddr> = addrs
+                .into_iter()
+                .filter(|addr| policy.allows_ip(addr.ip()))
+                .collect();
+            match blocked {
+                Some(addr) if allowed.is_empty() => Err(Box::new(BlockedAddress {
+                    host,
+                    address: addr.ip(),
+                })
+                    as Box<dyn std::error::Error + Send + Sync>),
+                _ => Ok(Box::new(allowed.into_iter()) as Addrs),
+            }
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("too many redirects (limit: {0})")]
+struct TooManyRedirects(usize);
+
+/// Redirect policy following up to `max_redirects` hops, refusing hops to literal internal hosts.
+/// With `proxied`, host names are resolved and refused if any address is internal.
+pub fn redirect_policy(
+    policy: Arc<AddressPolicy>,
+    max_redirects: usize,
+    proxied: bool,
+) -> redirect::Policy {
+    redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() > max_redirects {
+            return attempt.error(TooManyRedirects(max_redirects));
+        }
+        let checked = policy.check_url_host(attempt.url()).and_then(|()| {
+            if proxied {
+                policy.check_resolved_blocking(attempt.url())
+            } else {
+                Ok(())
+            }
+        });
+        match checked {
+            Ok(()) => attempt.follow(),
+            Err(blocked) => attempt.error(blocked),
+        }
+    })
+}
+
+/// Finds a `BlockedAddress` in the source chain of a request error.
+pub fn find_blocked<'a>(err: &'a (dyn std::error::Error + 'static)) -> Option<&'a BlockedAddress> {
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(blocked) = err.downcast_ref::<BlockedAddress>() {
+            return Some(blocked);
+        }
+        current = err.source();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_internal_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(
+                is_internal(ip.parse().unwrap()),
+                "{} should be internal",
+                ip
+            );
+        }
+        for ip in [
+            "93.184.216.34",
+            "100.128.0.1",
+            "2606:4700::1111",
+            "::ffff:8.8.8.8",
+        ] {
+            assert!(!is_internal(ip.parse().unwrap()), "{} should be public", ip);
+        }
+    }
+
+    #[test]
+    fn allowlist_reopens_selected_targets() {
+        let policy = AddressPolicy {
+            allow: vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "Intranet.Example".parse().unwrap(),
+            ],
+        };
+        let check = |url: &str| policy.check_url_host(&Url::parse(url).unwrap()).is_ok();
+        assert!(check("http://10.20.30.40/"));
+        assert!(check("http://intranet.example/"));
+        assert!(!check("http://192.168.0.1/"));
+        assert!(!check("http://localhost:6379/"));
+        assert!(!check("http://[::1]/"));
+        assert!(check("https://example.com/"));
+        assert!("10.0.0.0/33".parse::<AllowRule>().is_err());
+    }
+
+    #[tokio::test]
+    async fn proxied_targets_are_checked_after_resolution() {
+        // The literal check lets domain names through; a proxy would connect to localhost
+        let url = Url::parse("http://localhost:8080/admin").unwrap();
+        let blocked = AddressPolicy::default().check_resolved(&url).await;
+        assert!(blocked.unwrap_err().address.is_loopback());
+        assert!(AddressPolicy::default()
+            .check_resolved_blocking(&url)
+            .is_err());
+
+        let allowed = AddressPolicy {
+            allow: vec!["localhost".parse().unwrap()],
+        };
+        assert!(allowed.check_resolved(&url).await.is_ok());
+        let literal = Url::parse("http://93.184.216.34/").unwrap();
+        assert!(AddressPolicy::default()
+            .check_resolved(&literal)
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,254 @@
+// Size caps for downloaded response bodies. Bodies are streamed and the download stops as
+// soon as the cap for their content type is exceeded, so a huge file or an endless stream
+// never ends up in memory. reqwest decompresses gzip/deflate/brotli while streaming, so the
+// caps count decompressed bytes and also stop decompression bombs.
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use reqwest::Response;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use crate::utils::fetch_error::FetchError;
+use crate::utils::pdf::is_pdf;
+use crate::utils::progress::{self, report_bytes};
+
+const MIB: u64 = 1024 * 1024;
+
+// Minimum number of newly downloaded bytes between two download progress reports
+const BYTES_REPORT_STEP: u64 = 256 * 1024;
+
+// Bytes needed to recognize a body by its magic signature
+const SNIFF_LEN: usize = 512;
+
+/// What a body is, as far as its size cap is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BodyKind {
+    Html,
+    Text,
+    Json,
+    Pdf,
+    /// Anything else, e.g. scripts or binary types that are refused after download
+    Other,
+}
+
+impl BodyKind {
+    pub const ALL: [BodyKind; 5] = [
+        BodyKind::Html,
+        BodyKind::Text,
+        BodyKind::Json,
+        BodyKind::Pdf,
+        BodyKind::Other,
+    ];
+
+    /// Classifies by Content-Type, falling back to the PDF signature in `head`.
+    pub fn classify(content_type: Option<&str>, head: &[u8]) -> Self {
+        if is_pdf(content_type, head) {
+            return BodyKind::Pdf;
+        }
+        let mime = content_type
+            .and_then(|ct| ct.split(';').next())
+            .map(|ct| ct.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        match mime.as_str() {
+            "text/html" | "application/xhtml+xml" => BodyKind::Html,
+            "application/json" => BodyKind::Json,
+            m if m.ends_with("+json") => BodyKind::Json,
+            m if m.starts_with("text/") || m == "application/xml" || m.ends_with("+xml") => {
+                BodyKind::Text
+            }
+            // Servers that send no Content-Type mostly serve HTML
+            "" => BodyKind::Html,
+            _ => BodyKind::Other,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BodyKind::Html => "html",
+            BodyKind::Text => "text",
+            BodyKind::Json => "json",
+            BodyKind::Pdf => "pdf",
+            BodyKind::Other => "other",
+        }
+    }
+
+    /// Largest body of this kind that is downloaded.
+    pub fn max_bytes(self) -> u64 {
+        OVERRIDES
+            .read()
+            .unwrap()
+            .get(&self)
+            .copied()
+            .unwrap_or_else(|| self.default_max_bytes())
+    }
+
+    pub fn default_max_bytes(self) -> u64 {
+        match self {
+            BodyKind::Html => 10 * MIB,
+            BodyKind::Text => 10 * MIB,
+            BodyKind::Json => 10 * MIB,
+            BodyKind::Pdf => 500 * MIB,
+            BodyKind::Other => 10 * MIB,
+        }
+    }
+}
+
+impl FromStr for BodyKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        BodyKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown body kind '{}' (expected html, text, json, pdf or other)",
+                    s
+                )
+            })
+    }
+}
+
+static OVERRIDES: Lazy<RwLock<HashMap<BodyKind, u64>>> = Lazy::new(Default::default);
+
+/// Replaces the per-kind caps in bytes; kinds not listed use `BodyKind::default_max_bytes`.
+pub fn configure(limits: HashMap<BodyKind, u64>) {
+    *OVERRIDES.write().unwrap() = limits;
+}
+
+/// Streams the body until it ends or exceeds `limit` bytes, then aborts the download.
+/// Returns the bytes read (at most one chunk past the limit) and whether the limit was
+/// exceeded. `limit` is chosen once the first bytes are known.
+async fn read_up_to(
+    response: Response,
+    limit: impl FnOnce(&[u8]) -> u64,
+) -> reqwest::Result<(Vec<u8>, bool)> {
+    let total = response.content_length();
+    let report = progress::is_enabled();
+    let mut limit = Some(limit);
+    let mut max = u64::MAX;
+    let mut body = Vec::with_capacity(total.unwrap_or(0).min(16 * MIB) as usize);
+    let mut last_reported = 0u64;
+    let mut stream = response.bytes_stream();
+
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk?);
+        if body.len() >= SNIFF_LEN {
+            if let Some(limit) = limit.take() {
+                max = limit(&body);
+            }
+        }
+        if body.len() as u64 > max {
+            return Ok((body, true));
+        }
+        let read = body.len() as u64;
+        if report && read - last_reported >= BYTES_REPORT_STEP {
+            report_bytes(read, total);
+            last_reported = read;
+        }
+    }
+    // Short bodies end before the sniffing length
+    if let Some(limit) = limit.take() {
+        max = limit(&body);
+        if body.len() as u64 > max {
+            return Ok((body, true));
+        }
+    }
+
+    let read = body.len() as u64;
+    if report && read > last_reported {
+        report_bytes(read, total);
+    }
+    Ok((body, false))
+}
+
+/// Downloads the body of a page fetch within the cap for its content type.
+/// Bodies over the cap are refused with `FetchError::TooLarge`, by their declared
+/// Content-Length when possible and otherwise as soon as the cap is passed.
+pub async fn read_body(
+    url: &str,
+    content_type: Option<&str>,
+    response: Response,
+) -> Result<Vec<u8>, FetchError> {
+    let too_large =
+        |kind: BodyKind, bytes_read: u64, content_length: Option<u64>| FetchError::TooLarge {
+            url: url.to_string(),
+            content_type: content_type.map(str::to_string),
+            kind: kind.as_str(),
+            limit: kind.max_bytes(),
+            bytes_read,
+            content_length,
+        };
+
+    // Without Content-Encoding the declared length is the real one; refuse before downloading.
+    // (reqwest drops Content-Length from responses it decompresses.)
+    let content_length = response.content_length();
+    let declared_kind = BodyKind::classify(content_type, &[]);
+    if let Some(length) = content_length {
+        if declared_kind != BodyKind::Other && length > declared_kind.max_bytes() {
+            return Err(too_large(declared_kind, 0, content_length));
+        }
+    }
+
+    let mut kind = declared_kind;
+    let (body, exceeded) = read_up_to(response, |head| {
+        kind = BodyKind::classify(content_type, head);
+        kind.max_bytes()
+    })
+    .await
+    .map_err(|e| FetchError::from_reqwest(url, &e))?;
+    if exceeded {
+        return Err(too_large(kind, body.len() as u64, content_length));
+    }
+    Ok(body)
+}
+
+/// Downloads at most `max` bytes of the body and drops the rest, for documents where a
+/// prefix is still useful (robots.txt).
+pub async fn read_truncated(response: Response, max: u64) -> reqwest::Result<Vec<u8>> {
+    let (mut body, _) = read_up_to(response, |_| max).await?;
+    body.truncate(max as usize);
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{self, Reply};
+
+    // Serves an HTML body that never ends
+    async fn endless_server() -> String {
+        test_server::serve(|_| Reply::Endless {
+            head: "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n"
+                .to_string(),
+            chunk: vec![b'a'; 64 * 1024],
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn aborts_endless_body_at_cap() {
+        let url = endless_server().await;
+        let response = reqwest::get(&url).await.unwrap();
+
+        let err = read_body(&url, Some("text/html"), response)
+            .await
+            .unwrap_err();
+        let FetchError::TooLarge {
+            kind, bytes_read, ..
+        } = err
+        else {
+            panic!("expected TooLarge, got {:?}", err);
+        };
+        assert_eq!(kind, "html");
+        assert!(bytes_read > BodyKind::Html.max_bytes());
+        assert!(bytes_read < BodyKind::Html.max_bytes() + MIB);
+        assert_eq!(
+            BodyKind::classify(Some("application/octet-stream"), b"%PDF-1.7"),
+            BodyKind::Pdf
+        );
+    }
+}
@@ -0,0 +1,567 @@
+// Shared cache for search results and fetched pages. Entries are kept in memory (LRU,
+// bounded in bytes) and, when a cache directory is configured, also on disk so they survive
+// restarts. Every backend has its own TTL. A tool call with `noCache` skips lookups but
+// still stores what it fetched, so the next call sees the fresh result. Entries stored with
+// `put_revalidatable` outlive their TTL as stale entries, so an expired page can be
+// revalidated with a conditional request instead of being downloaded again.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+use crate::utils::http::Backend;
+
+pub const DEFAULT_MEMORY_BYTES: u64 = 64 * 1024 * 1024;
+pub const DEFAULT_DISK_BYTES: u64 = 512 * 1024 * 1024;
+
+/// How long a revalidatable entry is kept after it expires
+pub const STALE_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// Directory for the persistent cache; memory only when unset
+    pub dir: Option<PathBuf>,
+    pub memory_bytes: u64,
+    pub disk_bytes: u64,
+    /// TTL overrides; backends not listed use `default_ttl`, zero disables caching
+    pub ttls: HashMap<Backend, Duration>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            memory_bytes: DEFAULT_MEMORY_BYTES,
+            disk_bytes: DEFAULT_DISK_BYTES,
+            ttls: HashMap::new(),
+        }
+    }
+}
+
+pub fn default_ttl(backend: Backend) -> Duration {
+    let minutes = match backend {
+        // Search results change slowly, and repeating a query costs rate limit or quota
+        Backend::DuckDuckGo | Backend::Google | Backend::Felo => 60,
+        Backend::Jina | Backend::Fetch => 30,
+    };
+    Duration::from_secs(minutes * 60)
+}
+
+struct MemoryEntry {
+    value: Arc<str>,
+    expires: SystemTime,
+    /// Until when the entry is kept as a stale entry after it expires
+    stale_until: SystemTime,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct MemoryCache {
+    entries: HashMap<String, MemoryEntry>,
+    bytes: u64,
+    // Logical clock for LRU order
+    clock: u64,
+}
+
+impl MemoryCache {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry_size(key, &entry.value);
+        }
+    }
+
+    fn insert(&mut self, key: String, value: Arc<str>, expiry: Expiry, limit: u64) {
+        self.remove(&key);
+        let size = entry_size(&key, &value);
+        if size > limit {
+            return;
+        }
+        while self.bytes + size > limit {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+        let last_used = self.tick();
+        self.bytes += size;
+        self.entries.insert(
+            key,
+            MemoryEntry {
+                value,
+                expires: expiry.expires,
+                stale_until: expiry.stale_until,
+                last_used,
+            },
+        );
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Expiry {
+    expires: SystemTime,
+    stale_until: SystemTime,
+}
+
+fn entry_size(key: &str, value: &str) -> u64 {
+    (key.len() + value.len()) as u64
+}
+
+struct DiskFile {
+    size: u64,
+    last_used: u64,
+}
+
+/// Files of the disk cache, known from a directory scan at startup plus later writes.
+#[derive(Default)]
+struct DiskIndex {
+    files: HashMap<PathBuf, DiskFile>,
+    bytes: u64,
+    clock: u64,
+}
+
+impl DiskIndex {
+    fn scan(dir: &Path) -> Self {
+        let mut found = Vec::new();
+        for backend in Backend::ALL {
+            let Ok(entries) = std::fs::read_dir(dir.join(backend.as_str())) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().map_or(true, |ext| ext != "json") {
+                    continue;
+                }
+                if let Ok(meta) = entry.metadata() {
+                    let modified = meta.modified().unwrap_or(UNIX_EPOCH);
+                    found.push((path, meta.len(), modified));
+                }
+            }
+        }
+        // Oldest files are evicted first until they are used again
+        found.sort_by_key(|(_, _, modified)| *modified);
+        let mut index = DiskIndex::default();
+        for (path, size, _) in found {
+            index.touch(path, size);
+        }
+        index
+    }
+
+    fn touch(&mut self, path: PathBuf, size: u64) {
+        self.clock += 1;
+        let last_used = self.clock;
+        if let Some(old) = self.files.insert(path, DiskFile { size, last_used }) {
+            self.bytes -= old.size;
+        }
+        self.bytes += size;
+    }
+
+    fn forget(&mut self, path: &Path) {
+        if let Some(old) = self.files.remove(path) {
+            self.bytes -= old.size;
+        }
+    }
+
+    /// Removes least recently used files from the index until it fits `limit`; returns them.
+    fn evict(&mut self, limit: u64) -> Vec<PathBuf> {
+        let mut evicted = Vec::new();
+        while self.bytes > limit {
+            let Some(oldest) = self
+                .files
+                .iter()
+                .min_by_key(|(_, file)| file.last_used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            self.forget(&oldest);
+            evicted.push(oldest);
+        }
+        evicted
+    }
+}
+
+/// On-disk form of an entry.
+#[derive(Serialize, Deserialize)]
+struct DiskEntry {
+    key: String,
+    /// Expiry as seconds since the Unix epoch
+    expires: u64,
+    /// End of the stale period as seconds since the Unix epoch; absent when there is none
+    #[serde(default)]
+    stale_until: u64,
+    value: String,
+}
+
+struct Cache {
+    config: CacheConfig,
+    memory: Mutex<MemoryCache>,
+    disk: Option<Mutex<DiskIndex>>,
+}
+
+impl Cache {
+    fn new(config: CacheConfig) -> Result<Self> {
+        let disk = match &config.dir {
+            Some(dir) => {
+                for backend in Backend::ALL {
+                    std::fs::create_dir_all(dir.join(backend.as_str())).with_context(|| {
+                        format!("Cannot create cache directory {}", dir.display())
+                    })?;
+                }
+                let mut index = DiskIndex::scan(dir);
+                // The size limit may have been lowered since the files were written
+                for path in index.evict(config.disk_bytes) {
+                    let _ = std::fs::remove_file(path);
+                }
+                info!(
+                    "Disk cache at {}: {} entries, {} bytes",
+                    dir.display(),
+                    index.files.len(),
+                    index.bytes
+                );
+                Some(Mutex::new(index))
+            }
+            None => None,
+        };
+        Ok(Self {
+            config,
+            memory: Mutex::new(MemoryCache::default()),
+            disk,
+        })
+    }
+
+    fn ttl(&self, backend: Backend) -> Duration {
+        self.config
+            .ttls
+            .get(&backend)
+            .copied()
+            .unwrap_or_else(|| default_ttl(backend))
+    }
+
+    fn path_for(&self, backend: Backend, key: &str) -> Option<PathBuf> {
+        let dir = self.config.dir.as_ref()?;
+        let digest = Sha256::digest(key.as_bytes());
+        let mut name = String::with_capacity(69);
+        for byte in digest {
+            let _ = write!(name, "{:02x}", byte);
+        }
+        name.push_str(".json");
+        Some(dir.join(backend.as_str()).join(name))
+    }
+
+    /// Value for `key` and whether it is still fresh. Expired entries are returned only
+    /// with `allow_stale` and only during their stale period.
+    async fn get_raw(
+        &self,
+        backend: Backend,
+        key: &str,
+        allow_stale: bool,
+    ) -> Option<(Arc<str>, bool)> {
+        let now = SystemTime::now();
+        {
+            let mut memory = self.memory.lock().unwrap();
+            let clock = memory.tick();
+            match memory.entries.get_mut(key) {
+                Some(entry) if entry.expires > now || (allow_stale && entry.stale_until > now) => {
+                    entry.last_used = clock;
+                    return Some((entry.value.clone(), entry.expires > now));
+                }
+                Some(entry) if entry.stale_until > now => return None,
+                Some(_) => memory.remove(key),
+                None => {}
+            }
+        }
+
+        let (disk, path) = (self.disk.as_ref()?, self.path_for(backend, key)?);
+        let bytes = tokio::fs::read(&path).await.ok()?;
+        let entry = match serde_json::from_slice::<DiskEntry>(&bytes) {
+            Ok(entry) if entry.key == key => entry,
+            _ => {
+                self.remove_file(disk, &path).await;
+                return None;
+            }
+        };
+        let expires = UNIX_EPOCH + Duration::from_secs(entry.expires);
+        let stale_until = UNIX_EPOCH + Duration::from_secs(entry.stale_until.max(entry.expires));
+        if stale_until <= now {
+            self.remove_file(disk, &path).await;
+            return None;
+        }
+        let fresh = expires > now;
+        if !fresh && !allow_stale {
+            return None;
+        }
+        disk.lock().unwrap().touch(path, bytes.len() as u64);
+        let value: Arc<str> = entry.value.into();
+        self.memory.lock().unwrap().insert(
+            key.to_string(),
+            value.clone(),
+            Expiry {
+                expires,
+                stale_until,
+            },
+            self.config.memory_bytes,
+        );
+        Some((value, fresh))
+    }
+
+    /// Stores `value` for the backend's TTL; with `keep_stale` it is kept for
+    /// `STALE_RETENTION` more as a stale entry.
+    async fn put_raw(&self, backend: Backend, key: &str, value: String, keep_stale: bool) {
+        let expires = SystemTime::now() + self.ttl(backend);
+        let stale_until = if keep_stale {
+            expires + STALE_RETENTION
+        } else {
+            expires
+        };
+        let value: Arc<str> = value.into();
+        self.memory.lock().unwrap().insert(
+            key.to_string(),
+            value.clone(),
+            Expiry {
+                expires,
+                stale_until,
+            },
+            self.config.memory_bytes,
+        );
+
+        let (Some(disk), Some(path)) = (self.disk.as_ref(), self.path_for(backend, key)) else {
+            return;
+        };
+        let entry = DiskEntry {
+            key: key.to_string(),
+            expires: unix_secs(expires),
+            stale_until: if keep_stale {
+                unix_secs(stale_until)
+            } else {
+                0
+            },
+            value: value.to_string(),
+        };
+        let Ok(bytes) = serde_json::to_vec(&entry) else {
+            return;
+        };
+        let size = bytes.len() as u64;
+        if size > self.config.disk_bytes {
+            return;
+        }
+        // Write to a temporary file first so readers never see a partial entry
+        let tmp = path.with_extension("tmp");
+        let written = async {
+            tokio::fs::write(&tmp, &bytes).await?;
+            tokio::fs::rename(&tmp, &path).await
+        }
+        .await;
+        if let Err(e) = written {
+            warn!("Failed to write cache file {}: {}", path.display(), e);
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return;
+        }
+        let evicted = {
+            let mut index = disk.lock().unwrap();
+            index.touch(path, size);
+            index.evict(self.config.disk_bytes)
+        };
+        for path in evicted {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+    }
+
+    async fn remove_file(&self, disk: &Mutex<DiskIndex>, path: &Path) {
+        disk.lock().unwrap().forget(path);
+        let _ = tokio::fs::remove_file(path).await;
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+static CACHE: Lazy<RwLock<Arc<Cache>>> = Lazy::new(|| {
+    RwLock::new(Arc::new(
+        Cache::new(CacheConfig::default()).expect("memory cache needs no setup"),
+    ))
+});
+
+tokio::task_local! {
+    static NO_CACHE: bool;
+    // A test's own cache, so tests need not replace the process-wide one
+    #[cfg(test)]
+    static PRIVATE_CACHE: Arc<Cache>;
+}
+
+/// Replaces the cache. Entries in memory are dropped; the disk cache is picked up again.
+pub fn configure(config: CacheConfig) -> Result<()> {
+    let cache = Cache::new(config)?;
+    *CACHE.write().unwrap() = Arc::new(cache);
+    Ok(())
+}
+
+fn current() -> Arc<Cache> {
+    #[cfg(test)]
+    if let Ok(cache) = PRIVATE_CACHE.try_with(Arc::clone) {
+        return cache;
+    }
+    CACHE.read().unwrap().clone()
+}
+
+/// Runs `fut` against a new cache built from `config` instead of the configured one.
+#[cfg(test)]
+pub async fn with_private_cache<F: Future>(config: CacheConfig, fut: F) -> F::Output {
+    let cache = Arc::new(Cache::new(config).expect("test caches live in memory"));
+    PRIVATE_CACHE.scope(cache, fut).await
+}
+
+/// Runs `fut` with cache lookups skipped when `no_cache` is set (the `noCache` tool argument).
+pub async fn with_no_cache<F: Future>(no_cache: bool, fut: F) -> F::Output {
+    NO_CACHE.scope(no_cache, fut).await
+}
+
+/// Result of `lookup`.
+pub enum Lookup<T> {
+    Fresh(T),
+    /// Expired, but kept for revalidation
+    Stale(T),
+}
+
+fn skip_lookups() -> bool {
+    NO_CACHE.try_with(|skip| *skip).unwrap_or(false)
+}
+
+async fn read<T: DeserializeOwned>(
+    backend: Backend,
+    key: &str,
+    allow_stale: bool,
+) -> Option<Lookup<T>> {
+    if skip_lookups() {
+        return None;
+    }
+    let cache = current();
+    if cache.ttl(backend).is_zero() {
+        return None;
+    }
+    let key = format!("{}:{}", backend, key);
+    let (raw, fresh) = cache.get_raw(backend, &key, allow_stale).await?;
+    match serde_json::from_str(&raw) {
+        Ok(value) if fresh => {
+            debug!("Cache hit for {}", key);
+            Some(Lookup::Fresh(value))
+        }
+        Ok(value) => {
+            debug!("Stale cache hit for {}", key);
+            Some(Lookup::Stale(value))
+        }
+        Err(e) => {
+            // Written by an older version with a different layout
+            debug!("Ignoring unreadable cache entry {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Cached value of `backend` for `key`, if present and not expired.
+pub async fn get<T: DeserializeOwned>(backend: Backend, key: &str) -> Option<T> {
+    match read(backend, key, false).await? {
+        Lookup::Fresh(value) => Some(value),
+        Lookup::Stale(_) => None,
+    }
+}
+
+/// Like `get`, but also returns expired entries stored with `put_revalidatable`.
+pub async fn lookup<T: DeserializeOwned>(backend: Backend, key: &str) -> Option<Lookup<T>> {
+    read(backend, key, true).await
+}
+
+async fn write<T: Serialize>(backend: Backend, key: &str, value: &T, keep_stale: bool) {
+    let cache = current();
+    if cache.ttl(backend).is_zero() {
+        return;
+    }
+    match serde_json::to_string(value) {
+        Ok(raw) => {
+            cache
+                .put_raw(backend, &format!("{}:{}", backend, key), raw, keep_stale)
+                .await
+        }
+        Err(e) => warn!("Cannot cache value for {}: {}", key, e),
+    }
+}
+
+/// Stores `value` for `backend` under `key` for the backend's TTL.
+pub async fn put<T: Serialize>(backend: Backend, key: &str, value: &T) {
+    write(backend, key, value, false).await
+}
+
+/// Like `put`, but the entry stays available to `lookup` for `STALE_RETENTION` after it
+/// expires.
+pub async fn put_revalidatable<T: Serialize>(backend: Backend, key: &str, value: &T) {
+    write(backend, key, value, true).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn evicts_least_recently_used_and_persists_to_disk() {
+        let dir = std::env::temp_dir().join(format!("getweb-cache-test-{}", std::process::id()));
+        let config = CacheConfig {
+            dir: Some(dir.clone()),
+            memory_bytes: 100,
+            ..CacheConfig::default()
+        };
+        let cache = Cache::new(config.clone()).unwrap();
+        cache
+            .put_raw(Backend::Fetch, "a", "x".repeat(40), false)
+            .await;
+        cache
+            .put_raw(Backend::Fetch, "b", "y".repeat(40), false)
+            .await;
+        assert!(cache.get_raw(Backend::Fetch, "a", false).await.is_some());
+        // "b" is now the least recently used entry in memory
+        cache
+            .put_raw(Backend::Fetch, "c", "z".repeat(40), false)
+            .await;
+        {
+            let memory = cache.memory.lock().unwrap();
+            assert!(memory.entries.contains_key("a"));
+            assert!(!memory.entries.contains_key("b"));
+            assert!(memory.bytes <= 100);
+        }
+
+        // A new cache over the same directory still has every entry
+        let reopened = Cache::new(config).unwrap();
+        assert_eq!(
+            reopened.disk.as_ref().unwrap().lock().unwrap().files.len(),
+            3
+        );
+        assert_eq!(
+            reopened
+                .get_raw(Backend::Fetch, "b", false)
+                .await
+                .map(|(value, _)| value.to_string()),
+            Some("y".repeat(40))
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,169 @@
+// Operator-defined allow/deny lists of sites. Every fetch path checks the requested URL
+// (and the URL it ended up at after redirects); search tools drop results the policy
+// would refuse to fetch, so agents are not pointed at pages they cannot read.
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use url::Url;
+
+use crate::utils::fetch_error::FetchError;
+use crate::utils::wildcard;
+
+/// `example.com` matches the domain and its subdomains; patterns containing `*`
+/// are globs over the whole host name (`*.example.com`, `docs.*.org`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainPattern(String);
+
+impl FromStr for DomainPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let pattern = s.trim().trim_start_matches('.').trim_end_matches('.');
+        if pattern.is_empty()
+            || pattern.contains(|c: char| c.is_whitespace() || c == '/' || c == ':')
+        {
+            return Err(anyhow!(
+                "Invalid domain pattern '{}' (expected e.g. example.com or *.example.com)",
+                s
+            ));
+        }
+        Ok(DomainPattern(pattern.to_ascii_lowercase()))
+    }
+}
+
+impl fmt::Display for DomainPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl DomainPattern {
+    fn matches(&self, host: &str) -> bool {
+        if self.0.contains('*') {
+            return wildcard::matches(self.0.as_bytes(), host.as_bytes());
+        }
+        host == self.0
+            || (host.ends_with(&self.0) && host[..host.len() - self.0.len()].ends_with('.'))
+    }
+}
+
+/// Deny patterns win; with a non-empty allowlist, hosts must also match one of its patterns.
+#[derive(Debug, Clone, Default)]
+pub struct DomainPolicy {
+    pub allow: Vec<DomainPattern>,
+    pub deny: Vec<DomainPattern>,
+}
+
+impl DomainPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Why `host` is refused, or `None` if it may be fetched.
+    pub fn refusal(&self, host: &str) -> Option<String> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if let Some(pattern) = self.deny.iter().find(|p| p.matches(&host)) {
+            return Some(format!("Matches denied pattern '{}'", pattern));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|p| p.matches(&host)) {
+            return Some("Not on the list of allowed domains".to_string());
+        }
+        None
+    }
+
+    /// Whether a search result link may be shown. Links without a host only pass an empty allowlist.
+    pub fn allows_link(&self, link: &str) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        match Url::parse(link).ok().as_ref().and_then(Url::host_str) {
+            Some(host) => self.refusal(host).is_none(),
+            None => self.allow.is_empty(),
+        }
+    }
+}
+
+static POLICY: Lazy<RwLock<Arc<DomainPolicy>>> = Lazy::new(Default::default);
+
+#[cfg(test)]
+tokio::task_local! {
+    // A test's own policy, so tests need not replace the process-wide one
+    static PRIVATE_POLICY: Arc<DomainPolicy>;
+}
+
+/// Replaces the active policy.
+pub fn configure(policy: DomainPolicy) {
+    *POLICY.write().unwrap() = Arc::new(policy);
+}
+
+pub fn current() -> Arc<DomainPolicy> {
+    #[cfg(test)]
+    if let Ok(policy) = PRIVATE_POLICY.try_with(Arc::clone) {
+        return policy;
+    }
+    POLICY.read().unwrap().clone()
+}
+
+/// Runs `fut` with `policy` in place of the configured one.
+#[cfg(test)]
+pub async fn with_private_policy<F: std::future::Future>(
+    policy: DomainPolicy,
+    fut: F,
+) -> F::Output {
+    PRIVATE_POLICY.scope(Arc::new(policy), fut).await
+}
+
+/// Refuses `url` if its host is not allowed by the active policy.
+pub fn check_url(url: &Url) -> Result<(), FetchError> {
+    let Some(host) = url.host_str() else {
+        return Ok(());
+    };
+    match current().refusal(host) {
+        Some(reason) => Err(FetchError::Policy {
+            url: url.to_string(),
+            host: host.to_string(),
+            reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Keeps the search results whose link the active policy allows; returns them with the
+/// number of results removed.
+pub fn filter_results<T>(results: Vec<T>, link: impl Fn(&T) -> &str) -> (Vec<T>, usize) {
+    let policy = current();
+    let total = results.len();
+    let kept: Vec<T> = results
+        .into_iter()
+        .filter(|r| policy.allows_link(link(r)))
+        .collect();
+    let removed = total - kept.len();
+    (kept, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allow: &[&str], deny: &[&str]) -> DomainPolicy {
+        DomainPolicy {
+            allow: allow.iter().map(|p| p.parse().unwrap()).collect(),
+            deny: deny.iter().map(|p| p.parse().unwrap()).collect(),
+        }
+    }
+
+    #[test]
+    fn suffix_and_glob_patterns() {
+        let policy = policy(&["example.com", "docs.*.org"], &["*.ads.example.com"]);
+        assert!(policy.refusal("example.com").is_none());
+        assert!(policy.refusal("WWW.Example.com.").is_none());
+        assert!(policy.refusal("docs.python.org").is_none());
+        assert!(policy.refusal("badexample.com").is_some());
+        assert!(policy.refusal("x.ads.example.com").is_some());
+        assert!(policy.refusal("python.org").is_some());
+        assert!(!policy.allows_link("not a url"));
+    }
+}
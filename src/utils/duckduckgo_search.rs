@@ -1,22 +1,26 @@
 use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
 use once_cell::sync::Lazy;
 use rand::seq::SliceRandom;
 use regex::Regex;
-use reqwest::Client;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Mutex;
-use std::time::{Duration, Instant};
-use tokio::sync::Mutex as AsyncMutex; // Async mutex for rate limiting queue
 use tracing::debug;
 use url::Url;
 
+use crate::utils::domain_policy;
+use crate::utils::fetch_error::FetchError;
+use crate::utils::http::{self, Backend};
+use crate::utils::rate_limit;
+use crate::utils::retry;
+use crate::utils::revalidate::{self, Download, Validators};
+use crate::utils::robots;
+use crate::utils::search_provider::{
+    SearchPage, SearchProvider, SearchRequest, SearchResult, TimeRange, TimeUnit,
+};
+
 // Constants
 const RESULTS_PER_PAGE: u32 = 10;
-const MAX_CACHE_PAGES: usize = 5;
-const CACHE_DURATION: Duration = Duration::from_secs(5 * 60); // 5 minutes
 
 // Rotating User Agents
 static USER_AGENTS: &[&str] = &[
@@ -27,25 +31,6 @@ static USER_AGENTS: &[&str] = &[
     "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
 ];
 
-// Cache for search results
-static RESULTS_CACHE: Lazy<Mutex<HashMap<String, CacheEntry>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
-
-#[derive(Debug, Clone)]
-struct CacheEntry {
-    results: Vec<SearchResult>,
-    timestamp: Instant,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SearchResult {
-    pub title: String,
-    pub url: String,
-    pub snippet: String,
-    pub favicon: String,
-    pub display_url: String,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UrlMetadata {
     pub title: String,
@@ -55,75 +40,12 @@ pub struct UrlMetadata {
     pub url: String,
 }
 
-// HTTP client
-static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
-    Client::builder()
-        .timeout(Duration::from_secs(30))
-        .build()
-        .expect("Failed to create HTTP client")
-});
-
-// ---------------------------------------------------------------------------
-// Global DuckDuckGo rate limiting (FIFO queue, min 5s between outbound calls)
-// ---------------------------------------------------------------------------
-// Even with multiple concurrent search tool executions we must
-// ensure at least 5 seconds between actual HTTP requests to DuckDuckGo.
-// Implemented this as a lazily-initialized global async mutex holding the
-// timestamp of the last performed request. Tasks acquire the mutex in the
-// order they reach .await (Tokio's mutex wake-up is fair enough for FIFO
-// semantics here) and, if needed, sleep while holding the lock to preserve
-// ordering and guarantee spacing (not releasing the lock before sleep avoids
-// a race that could allow multiple sleepers to wake and fire simultaneously).
-// Cache hits bypass this limiter entirely since they do not perform a network
-// request.
-const DDG_BASE_INTERVAL: Duration = Duration::from_secs(5);
-static LAST_DDG_REQUEST: Lazy<AsyncMutex<Option<Instant>>> = Lazy::new(|| AsyncMutex::new(None));
-static DDG_WAITING_COUNT: AtomicUsize = AtomicUsize::new(0);
-
-async fn wait_for_duckduckgo_rate_limit() {
-    // Register this task in the queue and capture its position (1-based)
-    let position = DDG_WAITING_COUNT.fetch_add(1, Ordering::SeqCst) + 1;
-
-    // Compute required minimal interval based on queue position:
-    // 1..=3 => 5s, 4 =>10s, 5 =>15s, 6 =>20s, etc.
-    let required_interval = if position <= 3 {
-        DDG_BASE_INTERVAL
-    } else {
-        // (position - 2) * 5s yields: 4->10s, 5->15s, 6->20s ...
-        DDG_BASE_INTERVAL * (position as u32 - 2)
-    };
-
-    let mut last_guard = LAST_DDG_REQUEST.lock().await;
-    if let Some(prev) = *last_guard {
-        let elapsed = prev.elapsed();
-        if elapsed < required_interval {
-            tokio::time::sleep(required_interval - elapsed).await;
-        }
-    }
-    *last_guard = Some(Instant::now());
-
-    // Done; remove from waiting count
-    DDG_WAITING_COUNT.fetch_sub(1, Ordering::SeqCst);
-}
-
 /// Get a random user agent from the list
 fn get_random_user_agent() -> &'static str {
     let mut rng = rand::thread_rng();
     USER_AGENTS.choose(&mut rng).unwrap_or(&USER_AGENTS[0])
 }
 
-/// Generate a cache key for a search query and page
-fn get_cache_key(query: &str, page: u32) -> String {
-    format!("{}-{}", query, page)
-}
-
-/// Clear old entries from the cache
-fn clear_old_cache() {
-    let mut cache = RESULTS_CACHE.lock().unwrap();
-    let now = Instant::now();
-    cache.retain(|_, entry| now.duration_since(entry.timestamp) < CACHE_DURATION);
-}
-
 /// Extract the direct URL from a DuckDuckGo redirect URL
 fn extract_direct_url(duckduckgo_url: &str) -> String {
     // Handle relative URLs from DuckDuckGo
@@ -192,46 +114,83 @@ fn get_favicon_url(url: &str) -> String {
     }
 }
 
-/// Search DuckDuckGo and return results
-pub async fn duckduckgo_search(
-    query: &str,
-    page: u32,
-    num_results: u32,
-) -> Result<Vec<SearchResult>> {
-    // Clear old cache entries
-    clear_old_cache();
+/// DuckDuckGo's HTML results page, 10 results per page.
+pub struct DuckDuckGo;
 
-    // Calculate start index for pagination
-    let start_index = (page - 1) * RESULTS_PER_PAGE;
+impl SearchProvider for DuckDuckGo {
+    fn backend(&self) -> Backend {
+        Backend::DuckDuckGo
+    }
 
-    // Check cache first
-    let cache_key = get_cache_key(query, page);
-    {
-        let cache = RESULTS_CACHE.lock().unwrap();
-        if let Some(cached_results) = cache.get(&cache_key) {
-            if Instant::now().duration_since(cached_results.timestamp) < CACHE_DURATION {
-                let end_index = std::cmp::min(num_results as usize, cached_results.results.len());
-                return Ok(cached_results.results[..end_index].to_vec());
-            }
-        }
+    // Pages have a fixed size, so one cached page serves any number of results
+    fn cache_key(&self, request: &SearchRequest) -> String {
+        let mut request = request.clone();
+        request.count = RESULTS_PER_PAGE;
+        serde_json::to_string(&request).expect("search requests serialize")
     }
 
+    fn fetch<'a>(&'a self, request: &'a SearchRequest) -> BoxFuture<'a, Result<SearchPage>> {
+        Box::pin(fetch_results(request))
+    }
+}
+
+/// DuckDuckGo's `df` period covering `range`; it only knows the past day, week, month and
+/// year. `None` for longer ranges, which are not restricted at all.
+fn period(range: TimeRange) -> Option<&'static str> {
+    let days_per_unit = match range.unit {
+        TimeUnit::Day => 1,
+        TimeUnit::Week => 7,
+        TimeUnit::Month => 31,
+        TimeUnit::Year => 366,
+    };
+    match range.count.saturating_mul(days_per_unit) {
+        0..=1 => Some("d"),
+        2..=7 => Some("w"),
+        8..=31 => Some("m"),
+        32..=366 => Some("y"),
+        _ => None,
+    }
+}
+
+async fn fetch_results(request: &SearchRequest) -> Result<SearchPage> {
+    // Calculate start index for pagination
+    let start_index = request
+        .page
+        .saturating_sub(1)
+        .saturating_mul(RESULTS_PER_PAGE);
+
     // Get a random user agent
     let user_agent = get_random_user_agent();
 
     // Fetch results
-    let url = format!(
+    let mut url = format!(
         "https://duckduckgo.com/html/?q={}&s={}",
-        urlencoding::encode(query),
+        urlencoding::encode(&request.full_query()),
         start_index
     );
+    // DuckDuckGo regions combine country and language, e.g. `de-de` or `us-en`
+    if let Some(region) = &request.region {
+        let language = request.language.as_deref().unwrap_or("en");
+        url.push_str(&format!(
+            "&kl={}-{}",
+            urlencoding::encode(&region.to_ascii_lowercase()),
+            urlencoding::encode(&language.to_ascii_lowercase())
+        ));
+    }
+    if let Some(period) = request.time_range.and_then(period) {
+        url.push_str(&format!("&df={}", period));
+    }
+    let accept_language = match &request.language {
+        Some(language) => format!("{},en;q=0.5", language),
+        None => "en-US,en;q=0.9".to_string(),
+    };
 
     debug!("Fetching search results from: {}", url);
 
-    // Enforce global DuckDuckGo rate limit (only on cache miss path)
-    wait_for_duckduckgo_rate_limit().await;
+    // Rate limit only the cache miss path; cache hits make no request
+    rate_limit::acquire(Backend::DuckDuckGo).await?;
 
-    let response = HTTP_CLIENT
+    let request = http::client_for(Backend::DuckDuckGo)
         .get(&url)
         .header("User-Agent", user_agent)
         // Added browser-like headers to reduce rate limiting / bot detection
@@ -239,10 +198,9 @@ pub async fn duckduckgo_search(
             "Accept",
             "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
         )
-        .header("Accept-Language", "en-US,en;q=0.9")
-        .header("Accept-Encoding", "gzip, deflate, br")
-        .send()
-        .await?;
+        .header("Accept-Language", accept_language)
+        .header("Accept-Encoding", "gzip, deflate, br");
+    let response = retry::send(Backend::DuckDuckGo, request).await?;
 
     if !response.status().is_success() {
         return Err(anyhow!(
@@ -272,7 +230,15 @@ pub async fn duckduckgo_search(
         return Err(anyhow!("Request limit exceeded, try other tool for search"));
     }
 
-    let document = Html::parse_document(&html);
+    Ok(SearchPage {
+        results: parse_results(&html),
+        total_results: None,
+    })
+}
+
+/// Extracts the search results from a DuckDuckGo HTML results page
+fn parse_results(html: &str) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
 
     // Parse results - try multiple selectors
     let result_selectors = [
@@ -381,41 +347,14 @@ pub async fn duckduckgo_search(
                 title,
                 url: direct_link,
                 snippet,
-                favicon,
                 display_url,
+                favicon: (!favicon.is_empty()).then_some(favicon),
+                date_published: None,
             });
         }
     }
 
-    // Get paginated results
-    let end_index = std::cmp::min(num_results as usize, results.len());
-    let paginated_results = results[..end_index].to_vec();
-
-    // Cache the results
-    {
-        let mut cache = RESULTS_CACHE.lock().unwrap();
-        cache.insert(
-            cache_key,
-            CacheEntry {
-                results: paginated_results.clone(),
-                timestamp: Instant::now(),
-            },
-        );
-
-        // If cache is too big, remove oldest entries
-        if cache.len() > MAX_CACHE_PAGES {
-            let oldest_key = cache
-                .iter()
-                .min_by_key(|(_, entry)| entry.timestamp)
-                .map(|(key, _)| key.clone());
-
-            if let Some(key) = oldest_key {
-                cache.remove(&key);
-            }
-        }
-    }
-
-    Ok(paginated_results)
+    results
 }
 
 /// Clean HTML text by removing tags and decoding entities
@@ -441,11 +380,43 @@ pub async fn extract_url_metadata(url: &str) -> Result<UrlMetadata> {
 
     debug!("Extracting metadata from URL: {}", url);
 
-    let response = HTTP_CLIENT
+    let requested = Url::parse(url)?;
+    domain_policy::check_url(&requested)?;
+    robots::check(&requested, user_agent).await?;
+    let cache_key = format!("metadata:{}", url);
+    revalidate::cached(&cache_key, |validators| {
+        fetch_metadata(url, &requested, user_agent, validators)
+    })
+    .await
+}
+
+async fn fetch_metadata(
+    url: &str,
+    requested: &Url,
+    user_agent: &str,
+    validators: Option<Validators>,
+) -> Result<Download<UrlMetadata>> {
+    if let Some(host) = requested.host_str() {
+        rate_limit::acquire_host(host)
+            .await
+            .map_err(|e| FetchError::from_rate_limited(url, &e))?;
+    }
+    robots::wait_for_crawl_delay(requested, user_agent).await;
+
+    let mut request = http::client_for(Backend::Fetch)
         .get(url)
-        .header("User-Agent", user_agent)
-        .send()
-        .await?;
+        .header("User-Agent", user_agent);
+    if let Some(validators) = &validators {
+        request = validators.apply(request);
+    }
+    let response = retry::send(Backend::Fetch, request).await?;
+    // Redirects may have left the allowed domains or the pages robots.txt permits
+    domain_policy::check_url(response.url())?;
+    robots::check_redirected(requested, response.url(), user_agent).await?;
+
+    if let Some(not_modified) = Download::not_modified(validators.as_ref(), &response) {
+        return Ok(not_modified);
+    }
 
     if !response.status().is_success() {
         return Err(anyhow!(
@@ -455,8 +426,14 @@ pub async fn extract_url_metadata(url: &str) -> Result<UrlMetadata> {
         ));
     }
 
+    let validators = Validators::from_response(&response);
     let html = response.text().await?;
-    let document = Html::parse_document(&html);
+    Ok(Download::Modified(parse_metadata(&html, url), validators))
+}
+
+/// Extracts title, description, preview image and favicon of the page at `url`
+fn parse_metadata(html: &str, url: &str) -> UrlMetadata {
+    let document = Html::parse_document(html);
 
     // Extract metadata
     let title_selector = Selector::parse("title").unwrap();
@@ -499,13 +476,13 @@ pub async fn extract_url_metadata(url: &str) -> Result<UrlMetadata> {
         })
         .or_else(|| Some(get_favicon_url(url)));
 
-    Ok(UrlMetadata {
+    UrlMetadata {
         title,
         description,
         og_image,
         favicon,
         url: url.to_string(),
-    })
+    }
 }
 
 /// Resolve a relative URL to an absolute URL
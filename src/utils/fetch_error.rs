@@ -0,0 +1,385 @@
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::mcp::types::CallToolResult;
+use crate::utils::address_guard::{self, BlockedAddress};
+use crate::utils::content_guard::build_error_payload;
+use crate::utils::rate_limit::RateLimited;
+
+/// Why fetching a URL failed. Each variant maps to a stable `code` that clients can
+/// match on instead of the human-readable message.
+#[derive(Error, Debug, Clone)]
+pub enum FetchError {
+    /// Network failure or non-success HTTP status
+    #[error("{message}")]
+    Http {
+        url: String,
+        status: Option<u16>,
+        message: String,
+        hint: String,
+        /// Underlying network error, if any
+        detail: Option<String>,
+    },
+
+    #[error("Fetch cannot be performed for this type of content")]
+    UnsupportedBinary {
+        url: String,
+        content_type: Option<String>,
+        size: Option<u64>,
+    },
+
+    /// PDF that could not be parsed
+    #[error("{message}")]
+    PdfParse {
+        url: String,
+        content_type: Option<String>,
+        size: u64,
+        message: String,
+        hint: String,
+    },
+
+    /// Body larger than the cap for its content type; the download was aborted
+    #[error("Response body exceeds the {limit} byte limit for {kind} content")]
+    TooLarge {
+        url: String,
+        content_type: Option<String>,
+        kind: &'static str,
+        limit: u64,
+        /// Bytes downloaded before giving up; 0 when refused by Content-Length
+        bytes_read: u64,
+        content_length: Option<u64>,
+    },
+
+    #[error("Encrypted PDF is not supported")]
+    PdfEncrypted {
+        url: String,
+        content_type: Option<String>,
+        size: u64,
+    },
+
+    /// Body could not be turned into text (charset, JSON or HTML conversion)
+    #[error("{message}")]
+    Decode {
+        url: String,
+        message: String,
+        detail: String,
+    },
+
+    #[error("Request timed out")]
+    Timeout { url: String },
+
+    /// Refused by server policy before any request was made
+    #[error("Fetching this URL is not allowed")]
+    Blocked { url: String, reason: String },
+
+    /// Target resolves to a loopback, private, link-local or other internal address
+    #[error("Fetching private or internal network addresses is not allowed")]
+    PrivateAddress {
+        url: String,
+        host: String,
+        address: String,
+    },
+
+    /// Host refused by the operator's domain allow/deny policy
+    #[error("Fetching this site is not allowed by the domain policy")]
+    Policy {
+        url: String,
+        host: String,
+        reason: String,
+    },
+
+    /// Disallowed by the site's robots.txt (only with robots.txt compliance enabled)
+    #[error("Fetching this URL is disallowed by the site's robots.txt")]
+    RobotsDisallowed { url: String, rule: String },
+
+    /// Too many requests to this host are already waiting for the rate limiter
+    #[error("Rate limited, retry after {retry_after_secs} s")]
+    RateLimited { url: String, retry_after_secs: u64 },
+}
+
+impl FetchError {
+    /// Refuses URLs other than http(s) (file://, ftp://, data:, ...).
+    pub fn check_scheme(url: &url::Url) -> Result<(), FetchError> {
+        match url.scheme() {
+            "http" | "https" => Ok(()),
+            scheme => Err(FetchError::Blocked {
+                url: url.to_string(),
+                reason: format!(
+                    "Unsupported URL scheme '{}'; only http and https can be fetched",
+                    scheme
+                ),
+            }),
+        }
+    }
+
+    pub fn from_blocked(url: &str, blocked: &BlockedAddress) -> Self {
+        FetchError::PrivateAddress {
+            url: url.to_string(),
+            host: blocked.host.clone(),
+            address: blocked.address.to_string(),
+        }
+    }
+
+    pub fn from_rate_limited(url: &str, limited: &RateLimited) -> Self {
+        FetchError::RateLimited {
+            url: url.to_string(),
+            retry_after_secs: limited.retry_after_secs(),
+        }
+    }
+
+    /// Maps a reqwest send/body error, telling timeouts and refused internal targets
+    /// apart from other network failures.
+    pub fn from_reqwest(url: &str, err: &reqwest::Error) -> Self {
+        if let Some(blocked) = address_guard::find_blocked(err) {
+            return Self::from_blocked(url, blocked);
+        }
+        if err.is_timeout() {
+            return FetchError::Timeout {
+                url: url.to_string(),
+            };
+        }
+        FetchError::Http {
+            url: url.to_string(),
+            status: err.status().map(|s| s.as_u16()),
+            message: if err.is_body() || err.is_decode() {
+                "Failed to read HTTP response body".to_string()
+            } else {
+                "Network error during HTTP fetch".to_string()
+            },
+            hint: "Please verify the URL or try again later.".to_string(),
+            detail: Some(err.to_string()),
+        }
+    }
+
+    pub fn from_status(url: &str, status: reqwest::StatusCode) -> Self {
+        let code = status.as_u16();
+        FetchError::Http {
+            url: url.to_string(),
+            status: Some(code),
+            message: format!(
+                "HTTP error {}: {}",
+                code,
+                status.canonical_reason().unwrap_or("Unknown error")
+            ),
+            hint: if code == 404 {
+                "The resource was not found (404).".to_string()
+            } else {
+                "Please verify the URL and try again.".to_string()
+            },
+            detail: None,
+        }
+    }
+
+    /// Classifies a PDF extraction failure; password-protected documents get their own code.
+    pub fn from_pdf_error(url: &str, content_type: Option<String>, size: u64, error: &str) -> Self {
+        let error = error.to_ascii_lowercase();
+        if error.contains("encrypt") || error.contains("password") {
+            FetchError::PdfEncrypted {
+                url: url.to_string(),
+                content_type,
+                size,
+            }
+        } else {
+            FetchError::PdfParse {
+                url: url.to_string(),
+                content_type,
+                size,
+                message: "Failed to parse PDF content".to_string(),
+                hint: "Try another file or re-save the PDF to simplify its structure".to_string(),
+            }
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            FetchError::Http { .. } => "ERR_FETCH_HTTP",
+            FetchError::UnsupportedBinary { .. } => "ERR_FETCH_UNSUPPORTED_BINARY",
+            FetchError::PdfParse { .. } => "ERR_FETCH_PDF_PARSE",
+            FetchError::TooLarge { .. } => "ERR_FETCH_TOO_LARGE",
+            FetchError::PdfEncrypted { .. } => "ERR_FETCH_PDF_ENCRYPTED",
+            FetchError::Decode { .. } => "ERR_FETCH_DECODE",
+            FetchError::Timeout { .. } => "ERR_FETCH_TIMEOUT",
+            FetchError::Blocked { .. } => "ERR_FETCH_BLOCKED",
+            FetchError::PrivateAddress { .. } => "ERR_FETCH_PRIVATE_ADDRESS",
+            FetchError::Policy { .. } => "ERR_FETCH_POLICY",
+            FetchError::RobotsDisallowed { .. } => "ERR_FETCH_ROBOTS_DISALLOWED",
+            FetchError::RateLimited { .. } => "ERR_FETCH_RATE_LIMITED",
+        }
+    }
+
+    /// Whether repeating the same request later may succeed.
+    pub fn retryable(&self) -> bool {
+        match self {
+            FetchError::Timeout { .. } | FetchError::RateLimited { .. } => true,
+            FetchError::Http { status, .. } => match status {
+                Some(code) => *code == 408 || *code == 429 || *code >= 500,
+                None => true,
+            },
+            _ => false,
+        }
+    }
+
+    /// Variant-specific context (url, httpStatus, contentType, ...); unknown values are omitted.
+    pub fn details(&self) -> Value {
+        let mut details = match self {
+            FetchError::Http {
+                url,
+                status,
+                hint,
+                detail,
+                ..
+            } => json!({
+                "url": url,
+                "httpStatus": status,
+                "reason": status
+                    .and_then(|s| reqwest::StatusCode::from_u16(s).ok())
+                    .and_then(|s| s.canonical_reason()),
+                "hint": hint,
+                "error": detail,
+            }),
+            FetchError::UnsupportedBinary {
+                url,
+                content_type,
+                size,
+            } => json!({
+                "url": url,
+                "contentType": content_type,
+                "size": size,
+                "hint": "Binary content is not supported by this tool. Provide a text-based URL or use a tool that handles binary files.",
+            }),
+            FetchError::PdfParse {
+                url,
+                content_type,
+                size,
+                hint,
+                ..
+            } => json!({
+                "url": url,
+                "contentType": content_type,
+                "size": size,
+                "hint": hint,
+            }),
+            FetchError::TooLarge {
+                url,
+                content_type,
+                kind,
+                limit,
+                bytes_read,
+                content_length,
+            } => json!({
+                "url": url,
+                "contentType": content_type,
+                "kind": kind,
+                "limit": limit,
+                "bytesRead": bytes_read,
+                "contentLength": content_length,
+                "hint": "The response is too large to process. Try a smaller page or document.",
+            }),
+            FetchError::PdfEncrypted {
+                url,
+                content_type,
+                size,
+            } => json!({
+                "url": url,
+                "contentType": content_type,
+                "size": size,
+                "hint": "Try providing an unencrypted PDF or remove password protection",
+            }),
+            FetchError::Decode { url, detail, .. } => json!({
+                "url": url,
+                "error": detail,
+                "hint": "The content could not be converted to text.",
+            }),
+            FetchError::Timeout { url } => json!({
+                "url": url,
+                "hint": "The server did not respond in time. Please try again later.",
+            }),
+            FetchError::Blocked { url, reason } => json!({
+                "url": url,
+                "reason": reason,
+            }),
+            FetchError::PrivateAddress { url, host, address } => json!({
+                "url": url,
+                "host": host,
+                "address": address,
+                "hint": "The server refuses internal targets. Its operator can allow them with --allow-private-host.",
+            }),
+            FetchError::Policy { url, host, reason } => json!({
+                "url": url,
+                "host": host,
+                "reason": reason,
+                "hint": "Choose a source from another site.",
+            }),
+            FetchError::RobotsDisallowed { url, rule } => json!({
+                "url": url,
+                "rule": rule,
+                "hint": "The site does not permit automated access to this page.",
+            }),
+            FetchError::RateLimited {
+                url,
+                retry_after_secs,
+            } => json!({
+                "url": url,
+                "retryAfter": retry_after_secs,
+                "hint": "Too many requests to this site are queued. Retry after the given number of seconds.",
+            }),
+        };
+        if let Value::Object(map) = &mut details {
+            map.retain(|_, value| !value.is_null());
+        }
+        details
+    }
+
+    /// Machine-readable form used for `structuredContent` and `McpError.data`.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "code": self.code(),
+            "message": self.to_string(),
+            "retryable": self.retryable(),
+            "details": self.details(),
+        })
+    }
+
+    /// Tool error whose text keeps the "message + JSON line" layout for clients
+    /// that only read text content.
+    pub fn to_tool_result(&self) -> CallToolResult {
+        let text = build_error_payload(self.code(), &self.to_string(), self.details());
+        CallToolResult::error(text).with_structured_content(json!({ "error": self.to_json() }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn codes_and_retryability() {
+        let url = "https://example.com/";
+        let unavailable = FetchError::from_status(url, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(unavailable.code(), "ERR_FETCH_HTTP");
+        assert!(unavailable.retryable());
+        assert!(!FetchError::from_status(url, StatusCode::NOT_FOUND).retryable());
+        assert!(FetchError::from_status(url, StatusCode::TOO_MANY_REQUESTS).retryable());
+
+        let encrypted = FetchError::from_pdf_error(url, None, 10, "Document is Encrypted");
+        assert_eq!(encrypted.code(), "ERR_FETCH_PDF_ENCRYPTED");
+        assert!(!encrypted.retryable());
+        let limited = FetchError::RateLimited {
+            url: url.to_string(),
+            retry_after_secs: 3,
+        };
+        assert!(limited.retryable());
+
+        let file = url::Url::parse("file:///etc/passwd").unwrap();
+        let blocked = FetchError::check_scheme(&file).unwrap_err();
+        assert_eq!(blocked.code(), "ERR_FETCH_BLOCKED");
+
+        let json = unavailable.to_json();
+        assert_eq!(json["code"], "ERR_FETCH_HTTP");
+        assert_eq!(json["retryable"], true);
+        assert_eq!(json["details"]["httpStatus"], 503);
+        // Unknown values are left out rather than sent as null
+        assert!(json["details"].get("error").is_none());
+    }
+}
@@ -1,9 +1,18 @@
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
 use thiserror::Error;
 use tracing::{debug, error};
 
+use crate::utils::cache;
+use crate::utils::domain_policy;
+use crate::utils::fetch_error::FetchError;
+use crate::utils::http::{self, Backend};
+use crate::utils::rate_limit;
+use crate::utils::retry;
+use crate::utils::robots;
+
 #[derive(Error, Debug)]
 pub enum JinaReaderError {
     #[error("HTTP request error: {0}")]
@@ -14,6 +23,10 @@ pub enum JinaReaderError {
 
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+
+    /// Refused before sending: domain policy, robots.txt or rate limit
+    #[error(transparent)]
+    Refused(#[from] FetchError),
 }
 
 #[derive(Debug, Serialize)]
@@ -60,7 +73,7 @@ pub struct JinaUsage {
     pub tokens: Option<u32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct JinaReaderResponse {
     pub url: Option<String>,
     pub title: Option<String>,
@@ -80,6 +93,9 @@ pub struct JinaReaderParams {
     pub timeout: u32,
 }
 
+// Jina renders pages server-side and may take longer than the shared client's default timeout
+const JINA_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
 pub struct JinaReaderService {
     client: Client,
     api_key: String,
@@ -88,23 +104,53 @@ pub struct JinaReaderService {
 
 impl JinaReaderService {
     pub fn new(api_key: String) -> Self {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(60))
-            .build()
-            .expect("Failed to create HTTP client");
-
         Self {
-            client,
+            client: http::client_for(Backend::Jina),
             api_key,
             endpoint: "https://r.jina.ai/".to_string(),
         }
     }
 
+    // Marked sensitive so the key never shows up in debug output of the request
+    fn authorization(&self) -> Result<header::HeaderValue, JinaReaderError> {
+        let mut value = header::HeaderValue::from_str(&format!("Bearer {}", self.api_key))
+            .map_err(|_| JinaReaderError::Api("API key is not a valid header value".into()))?;
+        value.set_sensitive(true);
+        Ok(value)
+    }
+
     pub async fn read_url(
         &self,
         url: &str,
         params: &JinaReaderParams,
     ) -> Result<JinaReaderResponse, JinaReaderError> {
+        if let Ok(parsed) = url::Url::parse(url) {
+            domain_policy::check_url(&parsed)?;
+            robots::check(&parsed, &http::user_agent()).await?;
+        }
+        // Everything but the timeout changes what Jina returns
+        let cache_key = format!(
+            "{}:{}:{}:{}:{}",
+            params.return_format,
+            params.with_links_summary,
+            params.with_images_summary,
+            params.with_generated_alt,
+            url
+        );
+        if let Some(cached) = cache::get::<JinaReaderResponse>(Backend::Jina, &cache_key).await {
+            if let Some(final_url) = cached.url.as_deref().and_then(|u| url::Url::parse(u).ok()) {
+                domain_policy::check_url(&final_url)?;
+            }
+            return Ok(cached);
+        }
+        rate_limit::acquire(Backend::Jina)
+            .await
+            .map_err(|e| FetchError::from_rate_limited(url, &e))?;
+        // Jina fetches the page itself, but for the site it is still a request of ours
+        if let Ok(parsed) = url::Url::parse(url) {
+            robots::wait_for_crawl_delay(&parsed, &http::user_agent()).await;
+        }
+
         let request_body = JinaReaderRequest {
             url: url.to_string(),
             with_links_summary: if params.with_links_summary {
@@ -140,9 +186,10 @@ impl JinaReaderService {
         let mut request_builder = self
             .client
             .post(&self.endpoint)
+            .timeout(JINA_REQUEST_TIMEOUT)
             .header(header::CONTENT_TYPE, "application/json")
             .header(header::ACCEPT, "application/json")
-            .header(header::AUTHORIZATION, format!("Bearer {}", self.api_key));
+            .header(header::AUTHORIZATION, self.authorization()?);
 
         // Add optional headers based on parameters
         if params.with_links_summary {
@@ -164,18 +211,31 @@ impl JinaReaderService {
             request_builder = request_builder.header("X-Timeout", params.timeout.to_string());
         }
 
-        let response = request_builder
-            .json(&serde_json::json!({"url": url}))
-            .send()
-            .await?
-            .error_for_status();
+        let response = retry::send(
+            Backend::Jina,
+            request_builder.json(&serde_json::json!({"url": url})),
+        )
+        .await?
+        .error_for_status();
 
         match response {
             Ok(response) => {
                 let response_text = response.text().await?;
-                debug!("Received response from Jina Reader API: {}", response_text);
+                debug!(
+                    "Received {} bytes from Jina Reader API",
+                    response_text.len()
+                );
 
                 let api_response = serde_json::from_str::<JinaReaderApiResponse>(&response_text)?;
+                // Jina follows redirects on its side; the page it ended up on must be allowed too
+                if let Some(final_url) = api_response
+                    .data
+                    .url
+                    .as_deref()
+                    .and_then(|u| url::Url::parse(u).ok())
+                {
+                    domain_policy::check_url(&final_url)?;
+                }
 
                 // Convert to the expected response format
                 let reader_response = JinaReaderResponse {
@@ -186,6 +246,7 @@ impl JinaReaderService {
                     links: api_response.data.links,
                     images: api_response.data.images,
                 };
+                cache::put(Backend::Jina, &cache_key, &reader_response).await;
 
                 Ok(reader_response)
             }
@@ -1,7 +1,24 @@
+pub mod address_guard;
+pub mod body_limit;
+pub mod cache;
 pub mod content_guard;
+pub mod domain_policy;
 pub mod duckduckgo_search;
+pub mod fetch_error;
 pub mod google_search;
+pub mod http;
 pub mod jina_reader;
+pub mod page_store;
 pub mod pdf;
+pub mod progress;
+pub mod rate_limit;
 pub mod readability_extract;
+pub mod redact;
+pub mod retry;
+pub mod revalidate;
+pub mod robots;
 pub mod search_felo;
+pub mod search_provider;
+#[cfg(test)]
+pub mod test_server;
+pub mod wildcard;
@@ -0,0 +1,149 @@
+// Recently fetched pages, exposed to MCP clients as `web://{url}` resources.
+// Bounded by page count and total size; the oldest pages are evicted first.
+
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+pub const RESOURCE_SCHEME: &str = "web://";
+pub const RESOURCE_TEMPLATE: &str = "web://{url}";
+
+const MAX_STORED_PAGES: usize = 50;
+const MAX_STORED_BYTES: usize = 32 * 1024 * 1024; // 32 MiB of markdown in total
+
+#[derive(Debug, Clone)]
+pub struct StoredPage {
+    pub url: String,
+    pub title: Option<String>,
+    pub markdown: String,
+}
+
+impl StoredPage {
+    pub fn uri(&self) -> String {
+        resource_uri(&self.url)
+    }
+}
+
+#[derive(Default)]
+struct PageStore {
+    // Most recently stored page last
+    pages: VecDeque<StoredPage>,
+}
+
+impl PageStore {
+    fn insert(&mut self, url: &str, markdown: &str) {
+        if markdown.len() > MAX_STORED_BYTES {
+            return;
+        }
+
+        self.pages.retain(|page| page.url != url);
+        self.pages.push_back(StoredPage {
+            url: url.to_string(),
+            title: extract_title(markdown),
+            markdown: markdown.to_string(),
+        });
+
+        let mut total: usize = self.pages.iter().map(|page| page.markdown.len()).sum();
+        while self.pages.len() > MAX_STORED_PAGES || total > MAX_STORED_BYTES {
+            match self.pages.pop_front() {
+                Some(evicted) => total -= evicted.markdown.len(),
+                None => break,
+            }
+        }
+    }
+
+    fn get(&self, url: &str) -> Option<StoredPage> {
+        self.pages.iter().find(|page| page.url == url).cloned()
+    }
+}
+
+static PAGE_STORE: Lazy<Mutex<PageStore>> = Lazy::new(Default::default);
+
+/// Remembers the markdown of a fetched page, replacing an earlier copy of the same URL.
+pub fn store_page(url: &str, markdown: &str) {
+    PAGE_STORE.lock().unwrap().insert(url, markdown);
+}
+
+pub fn get_page(url: &str) -> Option<StoredPage> {
+    PAGE_STORE.lock().unwrap().get(url)
+}
+
+/// Stored pages, most recently fetched first.
+pub fn list_pages() -> Vec<StoredPage> {
+    PAGE_STORE
+        .lock()
+        .unwrap()
+        .pages
+        .iter()
+        .rev()
+        .cloned()
+        .collect()
+}
+
+pub fn resource_uri(url: &str) -> String {
+    format!("{}{}", RESOURCE_SCHEME, url)
+}
+
+/// Extracts the page URL from a `web://` resource URI. Accepts the URL verbatim
+/// (`web://https://example.com/a`) or percent-encoded as produced by expanding the
+/// `web://{url}` template (`web://https%3A%2F%2Fexample.com%2Fa`).
+pub fn parse_resource_uri(uri: &str) -> Option<String> {
+    let rest = uri.strip_prefix(RESOURCE_SCHEME)?;
+    let url = if rest.contains("://") {
+        rest.to_string()
+    } else {
+        urlencoding::decode(rest).ok()?.into_owned()
+    };
+
+    match url::Url::parse(&url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Some(url),
+        _ => None,
+    }
+}
+
+// First markdown heading, ignoring the "# Source: `url`" header of PDF extracts
+fn extract_title(markdown: &str) -> Option<String> {
+    markdown
+        .lines()
+        .take(50)
+        .filter_map(|line| line.trim().strip_prefix("# "))
+        .map(str::trim)
+        .find(|title| !title.is_empty() && !title.starts_with("Source:"))
+        .map(|title| title.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_the_oldest_pages_beyond_the_bounds() {
+        let mut store = PageStore::default();
+        for i in 0..MAX_STORED_PAGES + 5 {
+            store.insert(&format!("https://example.com/{}", i), "# Page");
+        }
+        assert_eq!(store.pages.len(), MAX_STORED_PAGES);
+        assert!(store.get("https://example.com/4").is_none());
+        assert!(store.get("https://example.com/5").is_some());
+
+        // Storing a URL again moves it to the back instead of adding a copy
+        store.insert("https://example.com/5", "# Again");
+        assert_eq!(store.pages.len(), MAX_STORED_PAGES);
+        assert_eq!(store.pages.back().unwrap().title.as_deref(), Some("Again"));
+
+        let half = "x".repeat(MAX_STORED_BYTES / 2);
+        store.insert("https://example.com/big-1", &half);
+        store.insert("https://example.com/big-2", &half);
+        assert!(store.get("https://example.com/big-1").is_some());
+        store.insert("https://example.com/small", "# Small");
+        assert!(store.get("https://example.com/big-1").is_none());
+        assert!(store.get("https://example.com/big-2").is_some());
+
+        store.insert(
+            "https://example.com/huge",
+            &"x".repeat(MAX_STORED_BYTES + 1),
+        );
+        assert!(store.get("https://example.com/huge").is_none());
+        assert!(store.get("https://example.com/small").is_some());
+    }
+}
@@ -2,15 +2,108 @@
 // Always keep this module small and dependency-light.
 
 use anyhow::Context;
+use pdf_extract::{MediaBox, OutputDev, OutputError, PlainTextOutput, Transform};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 /// Extracts text from a PDF stored fully in memory.
-/// This is a thin wrapper over the `pdf-extract` crate API.
-pub fn extract_text_from_pdf_mem(bytes: &[u8]) -> anyhow::Result<String> {
-    let text = pdf_extract::extract_text_from_mem(bytes)
+/// This is a thin wrapper over the `pdf-extract` crate API. Extraction stops before the next
+/// page once `cancelled` is set.
+pub fn extract_text_from_pdf_mem(bytes: &[u8], cancelled: &AtomicBool) -> anyhow::Result<String> {
+    let mut doc = pdf_extract::Document::load_mem(bytes)
         .context("failed to extract text from PDF bytes using pdf-extract")?;
+    // Documents encrypted with an empty user password open without one
+    if doc.is_encrypted() {
+        doc.decrypt("")
+            .context("PDF is encrypted and needs a password")?;
+    }
+    let mut text = String::new();
+    {
+        let mut output = Cancellable {
+            inner: PlainTextOutput::new(&mut text),
+            cancelled,
+        };
+        pdf_extract::output_doc(&doc, &mut output)
+            .context("failed to extract text from PDF bytes using pdf-extract")?;
+    }
     Ok(text)
 }
 
+/// Runs `extract_text_from_pdf_mem` on the blocking thread pool so large documents do not stall
+/// the async runtime. If the awaiting request is cancelled, the extraction stops at the next
+/// page and its result is discarded.
+pub async fn extract_text_from_pdf_blocking<B>(bytes: B) -> anyhow::Result<String>
+where
+    B: AsRef<[u8]> + Send + 'static,
+{
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let _cancel_on_drop = CancelOnDrop(cancelled.clone());
+    tokio::task::spawn_blocking(move || extract_text_from_pdf_mem(bytes.as_ref(), &cancelled))
+        .await
+        .context("PDF extraction task failed")?
+}
+
+// Sets the flag when the future awaiting the extraction is dropped (or finishes)
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+// Output device failing at the start of a page once extraction was cancelled
+struct Cancellable<'a, O> {
+    inner: O,
+    cancelled: &'a AtomicBool,
+}
+
+impl<O: OutputDev> OutputDev for Cancellable<'_, O> {
+    fn begin_page(
+        &mut self,
+        page_num: u32,
+        media_box: &MediaBox,
+        art_box: Option<(f64, f64, f64, f64)>,
+    ) -> Result<(), OutputError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(OutputError::IoError(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "PDF extraction cancelled",
+            )));
+        }
+        self.inner.begin_page(page_num, media_box, art_box)
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        self.inner.end_page()
+    }
+
+    fn output_character(
+        &mut self,
+        trm: &Transform,
+        width: f64,
+        spacing: f64,
+        font_size: f64,
+        char: &str,
+    ) -> Result<(), OutputError> {
+        self.inner
+            .output_character(trm, width, spacing, font_size, char)
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        self.inner.begin_word()
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        self.inner.end_word()
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        self.inner.end_line()
+    }
+}
+
 /// Returns true if given content-type or head indicates a PDF file.
 /// - Content-Type: application/pdf (case-insensitive, substring match)
 /// - Magic bytes: %PDF-
@@ -18,3 +111,58 @@ pub fn is_pdf(content_type: Option<&str>, head: &[u8]) -> bool {
     let ct = content_type.unwrap_or("").to_ascii_lowercase();
     ct.contains("application/pdf") || head.starts_with(b"%PDF-")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One-page PDF showing `text` in Helvetica, with a valid cross-reference table
+    fn tiny_pdf(text: &str) -> Vec<u8> {
+        let content = format!("BT /F1 12 Tf 20 100 Td ({}) Tj ET", text);
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 200 200] /Contents 4 0 R \
+             /Resources << /Font << /F1 5 0 R >> >> >>"
+                .to_string(),
+            format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                content.len(),
+                content
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        ];
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", index + 1, object).as_bytes());
+        }
+        let xref = pdf.len();
+        pdf.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+        );
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+
+    #[test]
+    fn cancelled_extraction_stops_before_the_next_page() {
+        let pdf = tiny_pdf("Hello");
+        let text = extract_text_from_pdf_mem(&pdf, &AtomicBool::new(false)).unwrap();
+        assert!(text.contains("Hello"), "{:?}", text);
+
+        let err = extract_text_from_pdf_mem(&pdf, &AtomicBool::new(true)).unwrap_err();
+        assert!(format!("{:#}", err).contains("cancelled"), "{:#}", err);
+    }
+}
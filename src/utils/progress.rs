@@ -0,0 +1,100 @@
+// Progress reporting for long-running tool work (downloads, PDF extraction, rate-limit queues).
+// The MCP layer installs a sink for the current request via `with_progress`; deep helpers call
+// `report_*` without threading a handle through every signature. Without a sink these are no-ops.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+/// Callback receiving `(progress, total, message)` for the current request.
+pub type ProgressSink = Arc<dyn Fn(f64, Option<f64>, Option<String>) + Send + Sync>;
+
+struct ProgressState {
+    sink: ProgressSink,
+    // Last reported value; numeric reports must increase it
+    last: Mutex<f64>,
+}
+
+tokio::task_local! {
+    static PROGRESS: Arc<ProgressState>;
+}
+
+/// Runs `fut` with `sink` installed as the progress target of the current task.
+pub async fn with_progress<F: Future>(sink: Option<ProgressSink>, fut: F) -> F::Output {
+    match sink {
+        Some(sink) => {
+            let state = Arc::new(ProgressState {
+                sink,
+                last: Mutex::new(0.0),
+            });
+            PROGRESS.scope(state, fut).await
+        }
+        None => fut.await,
+    }
+}
+
+fn emit(progress: Option<f64>, total: Option<f64>, message: Option<String>) {
+    let _ = PROGRESS.try_with(|state| {
+        let mut last = state.last.lock().unwrap();
+        let value = match progress {
+            Some(p) if p > *last => p,
+            Some(_) => return,
+            // Message-only updates keep the value: a step count would mix units with bytes
+            None => *last,
+        };
+        *last = value;
+        (state.sink)(value, total, message);
+    });
+}
+
+/// Reports downloaded bytes out of the expected total (usually Content-Length).
+pub fn report_bytes(read: u64, total: Option<u64>) {
+    let message = match total {
+        Some(total) => format!("Downloaded {} of {} bytes", read, total),
+        None => format!("Downloaded {} bytes", read),
+    };
+    emit(Some(read as f64), total.map(|t| t as f64), Some(message));
+}
+
+/// Reports a stage change without a numeric measure (e.g. "Extracting PDF text"). The
+/// progress value stays at the last one reported.
+pub fn report_message(message: impl Into<String>) {
+    emit(None, None, Some(message.into()));
+}
+
+/// Returns true when the current task has a progress sink installed.
+pub fn is_enabled() -> bool {
+    PROGRESS.try_with(|_| ()).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn messages_keep_the_byte_count() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink_reports = reports.clone();
+        let sink: ProgressSink = Arc::new(move |progress, total, message| {
+            sink_reports
+                .lock()
+                .unwrap()
+                .push((progress, total, message));
+        });
+
+        with_progress(Some(sink), async {
+            assert!(is_enabled());
+            report_bytes(100, Some(1000));
+            report_message("Extracting PDF text");
+            // Not an increase; dropped
+            report_bytes(50, Some(1000));
+            report_bytes(1000, Some(1000));
+        })
+        .await;
+        assert!(!is_enabled());
+
+        let reports = reports.lock().unwrap();
+        let values: Vec<f64> = reports.iter().map(|(progress, _, _)| *progress).collect();
+        assert_eq!(values, [100.0, 100.0, 1000.0]);
+        assert_eq!(reports[1].2.as_deref(), Some("Extracting PDF text"));
+    }
+}
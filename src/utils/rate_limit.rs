@@ -0,0 +1,283 @@
+// Politeness rate limiting for outbound requests. Search and reader backends share one
+// limiter each; page fetches get one per host. Each limiter lets `burst` requests through
+// back to back and then one per `min_interval`; callers reserve their slot up front, so
+// waiters are served in arrival order without holding a lock while they sleep. When
+// `max_queue` requests are already waiting, new ones fail fast with a retry-after hint.
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::Instant;
+
+use crate::utils::http::Backend;
+use crate::utils::progress::report_message;
+
+// Limiters of hosts that have been idle this long are forgotten once there are many
+const MAX_TRACKED_KEYS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Spacing between requests once the burst is used up; zero disables the limiter
+    pub min_interval: Duration,
+    /// Requests allowed back to back after an idle period
+    pub burst: u32,
+    /// Requests allowed to wait for a slot before new ones are refused
+    pub max_queue: usize,
+}
+
+impl RateLimit {
+    pub fn default_for(backend: Backend) -> Self {
+        let (interval_ms, burst, max_queue) = match backend {
+            // DuckDuckGo answers bursts of HTML scraping with CAPTCHAs
+            Backend::DuckDuckGo => (5000, 1, 10),
+            // Custom Search JSON API allows 100 queries/day on the free tier
+            Backend::Google => (1000, 5, 20),
+            Backend::Felo => (2000, 1, 10),
+            Backend::Jina => (300, 5, 20),
+            // Per host, so fetching from many sites stays parallel
+            Backend::Fetch => (1000, 3, 10),
+        };
+        RateLimit {
+            min_interval: Duration::from_millis(interval_ms),
+            burst,
+            max_queue,
+        }
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = anyhow::Error;
+
+    /// `SECS[:BURST[:QUEUE]]`, e.g. `5`, `0.5:4` or `1:3:20`; omitted parts default to 1 and 10.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.trim().split(':');
+        let secs = parts
+            .next()
+            .and_then(|p| p.trim().parse::<f64>().ok())
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .ok_or_else(|| anyhow!("Invalid interval in '{}' (expected seconds, e.g. 0.5)", s))?;
+        let mut next_number = |name: &str, default: usize| match parts.next() {
+            None => Ok(default),
+            Some(p) => p
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| anyhow!("Invalid {} in '{}'", name, s)),
+        };
+        let burst = next_number("burst size", 1)?.max(1);
+        let max_queue = next_number("queue depth", 10)?;
+        if parts.next().is_some() {
+            return Err(anyhow!("Expected SECS[:BURST[:QUEUE]], got '{}'", s));
+        }
+        Ok(RateLimit {
+            min_interval: Duration::from_secs_f64(secs),
+            burst: burst.min(u32::MAX as usize) as u32,
+            max_queue,
+        })
+    }
+}
+
+/// Refusal when the queue for a limiter is full.
+#[derive(Error, Debug, Clone)]
+#[error("Rate limited ({key}), retry after {} s", retry_after_secs(*.retry_after))]
+pub struct RateLimited {
+    pub key: String,
+    pub retry_after: Duration,
+}
+
+impl RateLimited {
+    pub fn retry_after_secs(&self) -> u64 {
+        retry_after_secs(self.retry_after)
+    }
+}
+
+fn retry_after_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs_f64().ceil().max(1.0) as u64
+}
+
+struct Bucket {
+    /// When the next request would be due if no burst credit were left (GCRA)
+    next_due: Instant,
+    waiting: usize,
+}
+
+static OVERRIDES: Lazy<RwLock<HashMap<Backend, RateLimit>>> = Lazy::new(Default::default);
+static BUCKETS: Lazy<Mutex<HashMap<String, Bucket>>> = Lazy::new(Default::default);
+
+/// Replaces the per-backend limits; backends not listed use `RateLimit::default_for`.
+pub fn configure(limits: HashMap<Backend, RateLimit>) {
+    *OVERRIDES.write().unwrap() = limits;
+}
+
+pub fn limit_for(backend: Backend) -> RateLimit {
+    OVERRIDES
+        .read()
+        .unwrap()
+        .get(&backend)
+        .copied()
+        .unwrap_or_else(|| RateLimit::default_for(backend))
+}
+
+/// Waits for a request slot of `backend`. Not for `Backend::Fetch`, see `acquire_host`.
+pub async fn acquire(backend: Backend) -> Result<(), RateLimited> {
+    acquire_key(backend.as_str().to_string(), limit_for(backend)).await
+}
+
+/// Waits for a page fetch slot for `host`; every host has its own limiter.
+pub async fn acquire_host(host: &str) -> Result<(), RateLimited> {
+    acquire_key(
+        format!("{}:{}", Backend::Fetch, host.to_ascii_lowercase()),
+        limit_for(Backend::Fetch),
+    )
+    .await
+}
+
+/// Waits for a slot of the limiter requests of `backend` to `url` go through.
+pub async fn acquire_for(backend: Backend, url: &url::Url) -> Result<(), RateLimited> {
+    match (backend, url.host_str()) {
+        (Backend::Fetch, Some(host)) => acquire_host(host).await,
+        (Backend::Fetch, None) => Ok(()),
+        _ => acquire(backend).await,
+    }
+}
+
+// Decrements the queue count even if the waiting request is cancelled. A cancelled request
+// also gives its reservation back, unless a later request has already reserved after it.
+struct QueueSlot<'a> {
+    key: &'a str,
+    // `next_due` before and after the reservation
+    before: Instant,
+    after: Instant,
+    served: bool,
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(bucket) = BUCKETS.lock().unwrap().get_mut(self.key) {
+            bucket.waiting = bucket.waiting.saturating_sub(1);
+            if !self.served && bucket.next_due == self.after {
+                bucket.next_due = self.before;
+            }
+        }
+    }
+}
+
+async fn acquire_key(key: String, limit: RateLimit) -> Result<(), RateLimited> {
+    if limit.min_interval.is_zero() {
+        return Ok(());
+    }
+
+    let (start, position, before, after) = {
+        let mut buckets = BUCKETS.lock().unwrap();
+        let now = Instant::now();
+        if buckets.len() >= MAX_TRACKED_KEYS && !buckets.contains_key(&key) {
+            buckets.retain(|_, bucket| bucket.waiting > 0 || bucket.next_due > now);
+        }
+        let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+            next_due: now,
+            waiting: 0,
+        });
+
+        let due = bucket.next_due.max(now);
+        let burst_window = limit.min_interval * (limit.burst - 1);
+        let start = due
+            .checked_sub(burst_window)
+            .map_or(now, |start| start.max(now));
+        if start <= now {
+            bucket.next_due = due + limit.min_interval;
+            return Ok(());
+        }
+        if bucket.waiting >= limit.max_queue {
+            // A place frees up when the first queued request starts
+            let first_start = start
+                .checked_sub(limit.min_interval * bucket.waiting as u32)
+                .unwrap_or(now);
+            return Err(RateLimited {
+                key,
+                retry_after: first_start.saturating_duration_since(now),
+            });
+        }
+        bucket.waiting += 1;
+        bucket.next_due = due + limit.min_interval;
+        (start, bucket.waiting, due, bucket.next_due)
+    };
+
+    let mut slot = QueueSlot {
+        key: &key,
+        before,
+        after,
+        served: false,
+    };
+    let wait = start.saturating_duration_since(Instant::now());
+    if position > 1 {
+        report_message(format!(
+            "Queued for {} rate limit at position {}",
+            key, position
+        ));
+    }
+    report_message(format!(
+        "Waiting {:.1}s for {} rate limit",
+        wait.as_secs_f64(),
+        key
+    ));
+    tokio::time::sleep_until(start).await;
+    slot.served = true;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn bursts_then_spaces_and_refuses_when_queue_is_full() {
+        let interval = Duration::from_millis(300);
+        let limit = RateLimit {
+            min_interval: interval,
+            burst: 2,
+            max_queue: 1,
+        };
+        let key = "test:bursts".to_string();
+        let started = Instant::now();
+
+        acquire_key(key.clone(), limit).await.unwrap();
+        acquire_key(key.clone(), limit).await.unwrap();
+        assert!(started.elapsed() < interval);
+
+        let queued = tokio::spawn(acquire_key(key.clone(), limit));
+        tokio::task::yield_now().await;
+        let refused = acquire_key(key.clone(), limit).await.unwrap_err();
+        assert!(refused.retry_after <= interval);
+
+        queued.await.unwrap().unwrap();
+        assert!(started.elapsed() >= interval);
+        assert_eq!("0.5:4".parse::<RateLimit>().unwrap().burst, 4);
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiter_gives_its_slot_back() {
+        let interval = Duration::from_millis(300);
+        let limit = RateLimit {
+            min_interval: interval,
+            burst: 1,
+            max_queue: 1,
+        };
+        let key = "test:cancelled".to_string();
+        let started = Instant::now();
+
+        acquire_key(key.clone(), limit).await.unwrap();
+        let cancelled = tokio::spawn(acquire_key(key.clone(), limit));
+        tokio::task::yield_now().await;
+        cancelled.abort();
+        let _ = cancelled.await;
+
+        // Takes the cancelled request's place in the queue and its start time
+        acquire_key(key.clone(), limit).await.unwrap();
+        let elapsed = started.elapsed();
+        assert!(elapsed >= interval);
+        assert!(elapsed < interval * 2);
+    }
+}
@@ -1,17 +1,24 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use reqwest::Client;
 use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
+use std::sync::RwLock;
 use tracing::{info, warn};
 
-// Firefox ESR User-Agent string to reduce server-side variance
-pub const FIREFOX_UA: &str =
-    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:115.0) Gecko/20100101 Firefox/115.0";
+use crate::utils::address_guard;
+use crate::utils::body_limit;
+use crate::utils::domain_policy;
+use crate::utils::fetch_error::FetchError;
+use crate::utils::http::{self, Backend};
+use crate::utils::rate_limit;
+use crate::utils::retry;
+use crate::utils::revalidate::{self, Download, Validators};
+use crate::utils::robots;
 
 /// Kind of extracted textual content
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum ExtractionKind {
     /// Main content parsed from HTML via selectors + html2text
     HtmlMain,
@@ -24,7 +31,7 @@ pub enum ExtractionKind {
 }
 
 /// Structured result of extraction
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtractedContent {
     pub text: String,
     pub content_type: Option<String>,
@@ -32,64 +39,80 @@ pub struct ExtractedContent {
     pub main_fragment_used: bool,
 }
 
-// Local HTTP client with 30s timeout
-static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
-    Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .expect("Failed to create HTTP client")
-});
-
 /// Fetch textual content from the given URL.
 /// Implements HTTP, binary/PDF guards, decoding with chardetng/encoding_rs, logging and HTML main-content extraction.
-pub async fn fetch_url_content(url: &url::Url, extract_main: bool) -> Result<ExtractedContent> {
-    use crate::utils::content_guard::{build_error_payload, detect_binary, BinaryDetection};
-    use crate::utils::pdf::{extract_text_from_pdf_mem, is_pdf};
+pub async fn fetch_url_content(
+    url: &url::Url,
+    extract_main: bool,
+) -> Result<ExtractedContent, FetchError> {
+    address_guard::check_fetch_target(url).await?;
+    domain_policy::check_url(url)?;
+    let user_agent = http::user_agent();
+    robots::check(url, &user_agent).await?;
+
+    let mode = if extract_main { "main" } else { "full" };
+    let cache_key = format!("extract:{}:{}", mode, url);
+    revalidate::cached(&cache_key, |validators| {
+        download_and_extract(url, extract_main, &user_agent, validators)
+    })
+    .await
+}
+
+async fn download_and_extract(
+    url: &url::Url,
+    extract_main: bool,
+    user_agent: &str,
+    validators: Option<Validators>,
+) -> Result<Download<ExtractedContent>, FetchError> {
+    if let Some(host) = url.host_str() {
+        rate_limit::acquire_host(host)
+            .await
+            .map_err(|e| FetchError::from_rate_limited(url.as_str(), &e))?;
+    }
+    robots::wait_for_crawl_delay(url, user_agent).await;
 
     // Start fetch logging
     info!(target: "readability_extract", url = %url, "Starting HTTP fetch");
 
-    // Execute request with fixed Firefox UA
-    let response = HTTP_CLIENT
-        .get(url.as_str())
-        .header("User-Agent", FIREFOX_UA)
-        .send()
-        .await
-        .map_err(|e| {
-            // Standardize network/timeout as HTTP error for tool layer
-            let payload = build_error_payload(
-                "ERR_FETCH_HTTP",
-                "Network error during HTTP fetch",
-                serde_json::json!({
-                    "url": url.as_str(),
-                    "hint": "Please verify the URL or try again later.",
-                    "error": e.to_string()
-                }),
-            );
-            warn!(target: "readability_extract", url = %url, "HTTP transport error: {}", e);
-            anyhow!(payload)
-        })?;
+    // Execute request; the shared client supplies the browser-like User-Agent
+    let mut request = http::client_for(Backend::Fetch).get(url.as_str());
+    if let Some(validators) = &validators {
+        request = validators.apply(request);
+    }
+    let response = retry::send(Backend::Fetch, request).await.map_err(|e| {
+        warn!(target: "readability_extract", url = %url, "HTTP transport error: {}", e);
+        FetchError::from_reqwest(url.as_str(), &e)
+    })?;
+    // Redirects may have left the allowed domains or the pages robots.txt permits
+    domain_policy::check_url(response.url())?;
+    robots::check_redirected(url, response.url(), user_agent).await?;
+
+    if let Some(not_modified) = Download::not_modified(validators.as_ref(), &response) {
+        return Ok(not_modified);
+    }
 
-    // Non-success HTTP status => standardized error payload
+    // Non-success HTTP status => standardized error
     if !response.status().is_success() {
         let status = response.status();
-        let code_num = status.as_u16();
-        let reason = status.canonical_reason().unwrap_or("Unknown error");
-        let message = format!("HTTP error {}: {}", code_num, reason);
-        let payload = build_error_payload(
-            "ERR_FETCH_HTTP",
-            &message,
-            serde_json::json!({
-                "url": url.as_str(),
-                "httpStatus": code_num,
-                "reason": reason,
-                "hint": if code_num == 404 { "The resource was not found (404)." } else { "Please verify the URL and try again." }
-            }),
-        );
-        warn!(target: "readability_extract", url = %url, status = code_num, "HTTP non-success status");
-        return Err(anyhow!(payload));
+        warn!(target: "readability_extract", url = %url, status = status.as_u16(), "HTTP non-success status");
+        return Err(FetchError::from_status(url.as_str(), status));
     }
 
+    let validators = Validators::from_response(&response);
+    let content = extract(url, extract_main, response).await?;
+    Ok(Download::Modified(content, validators))
+}
+
+/// Downloads the body of a successful response and extracts its text.
+async fn extract(
+    url: &url::Url,
+    extract_main: bool,
+    response: reqwest::Response,
+) -> Result<ExtractedContent, FetchError> {
+    use crate::utils::content_guard::{detect_binary, BinaryDetection};
+    use crate::utils::pdf::{extract_text_from_pdf_blocking, is_pdf};
+    use crate::utils::progress::report_message;
+
     // Capture Content-Type header early
     let content_type_header = response
         .headers()
@@ -97,20 +120,13 @@ pub async fn fetch_url_content(url: &url::Url, extract_main: bool) -> Result<Ext
         .and_then(|ct| ct.to_str().ok())
         .map(|s| s.to_string());
 
-    // Read body bytes with standardized error payload and warn logging
-    let body_bytes = response.bytes().await.map_err(|e| {
-        let payload = build_error_payload(
-            "ERR_FETCH_HTTP",
-            "Failed to read HTTP response body",
-            serde_json::json!({
-                "url": url.as_str(),
-                "hint": "The server closed connection or returned invalid body.",
-                "error": e.to_string()
-            }),
-        );
-        warn!(target: "readability_extract", url = %url, "Body read failed: {}", e);
-        anyhow!(payload)
-    })?;
+    // Read body bytes within the size cap for the content type
+    let body_bytes = body_limit::read_body(url.as_str(), content_type_header.as_deref(), response)
+        .await
+        .map_err(|e| {
+            warn!(target: "readability_extract", url = %url, code = e.code(), "Body read failed: {}", e);
+            e
+        })?;
     let size = body_bytes.len();
     info!(target: "readability_extract", url = %url, size = size, ct = ?content_type_header, "HTTP fetch completed");
 
@@ -118,27 +134,12 @@ pub async fn fetch_url_content(url: &url::Url, extract_main: bool) -> Result<Ext
     let head_len = std::cmp::min(512, body_bytes.len());
     let head = &body_bytes[..head_len];
 
-    // PDF handling with size guard
-    const PDF_LIMIT_BYTES: u64 = 500 * 1024 * 1024; // 500 MiB
+    // PDF handling
     if is_pdf(content_type_header.as_deref(), head) {
-        if (size as u64) > PDF_LIMIT_BYTES {
-            let payload = build_error_payload(
-                "ERR_FETCH_PDF_PARSE",
-                "PDF exceeds the allowed size limit",
-                serde_json::json!({
-                    "url": url.as_str(),
-                    "contentType": content_type_header.clone().unwrap_or_else(|| "unknown".to_string()),
-                    "size": size,
-                    "limit": PDF_LIMIT_BYTES,
-                }),
-            );
-            info!(target: "readability_extract", url = %url, size = size, limit = PDF_LIMIT_BYTES, "PDF too large; refusing");
-            return Err(anyhow!(payload));
-        }
-
         info!(target: "readability_extract", url = %url, size = size, "Starting PDF text extraction");
+        report_message(format!("Extracting PDF text ({} bytes)", size));
         let started = std::time::Instant::now();
-        match extract_text_from_pdf_mem(&body_bytes) {
+        match extract_text_from_pdf_blocking(body_bytes).await {
             Ok(text) => {
                 info!(target: "readability_extract", url = %url, elapsed_ms = started.elapsed().as_millis() as u64, "PDF extraction succeeded");
                 return Ok(ExtractedContent {
@@ -149,33 +150,14 @@ pub async fn fetch_url_content(url: &url::Url, extract_main: bool) -> Result<Ext
                 });
             }
             Err(err) => {
-                let err_str = err.to_string().to_ascii_lowercase();
-                let (code, message, hint) =
-                    if err_str.contains("encrypt") || err_str.contains("password") {
-                        (
-                            "ERR_FETCH_PDF_ENCRYPTED",
-                            "Encrypted PDF is not supported",
-                            "Try providing an unencrypted PDF or remove password protection",
-                        )
-                    } else {
-                        (
-                            "ERR_FETCH_PDF_PARSE",
-                            "Failed to parse PDF content",
-                            "Try another file or re-save the PDF to simplify its structure",
-                        )
-                    };
-                let payload = build_error_payload(
-                    code,
-                    message,
-                    serde_json::json!({
-                        "url": url.as_str(),
-                        "contentType": content_type_header.clone().unwrap_or_else(|| "unknown".to_string()),
-                        "size": size,
-                        "hint": hint,
-                    }),
+                let err = FetchError::from_pdf_error(
+                    url.as_str(),
+                    content_type_header,
+                    size as u64,
+                    &err.to_string(),
                 );
-                warn!(target: "readability_extract", url = %url, code = code, "PDF extraction failed");
-                return Err(anyhow!(payload));
+                warn!(target: "readability_extract", url = %url, code = err.code(), "PDF extraction failed");
+                return Err(err);
             }
         }
     }
@@ -183,20 +165,12 @@ pub async fn fetch_url_content(url: &url::Url, extract_main: bool) -> Result<Ext
     // Binary guard for non-PDF binaries
     match detect_binary(content_type_header.as_deref(), head) {
         BinaryDetection::Binary { content_type } => {
-            let ct_effective = content_type
-                .or(content_type_header.clone())
-                .unwrap_or_else(|| "unknown".to_string());
-            let payload = build_error_payload(
-                "ERR_FETCH_UNSUPPORTED_BINARY",
-                "Fetch cannot be performed for this type of content",
-                serde_json::json!({
-                    "url": url.as_str(),
-                    "contentType": ct_effective,
-                    "size": size,
-                }),
-            );
             info!(target: "readability_extract", url = %url, size = size, "Binary content detected; refusing");
-            return Err(anyhow!(payload));
+            return Err(FetchError::UnsupportedBinary {
+                url: url.to_string(),
+                content_type: content_type.or(content_type_header),
+                size: Some(size as u64),
+            });
         }
         BinaryDetection::Text => {}
     }
@@ -204,17 +178,12 @@ pub async fn fetch_url_content(url: &url::Url, extract_main: bool) -> Result<Ext
     // At this point, treat as textual. Decode to UTF-8 using charset param or chardetng detection.
     info!(target: "readability_extract", url = %url, "Starting textual decode");
     let decoded = decode_to_utf8(&body_bytes, content_type_header.as_deref()).map_err(|e| {
-        let payload = build_error_payload(
-            "ERR_FETCH_UNKNOWN",
-            "Failed to decode textual content to UTF-8",
-            serde_json::json!({
-                "url": url.as_str(),
-                "hint": "The page encoding could not be reliably decoded.",
-                "error": e.to_string()
-            }),
-        );
         warn!(target: "readability_extract", url = %url, "Decoding failed: {}", e);
-        anyhow!(payload)
+        FetchError::Decode {
+            url: url.to_string(),
+            message: "Failed to decode textual content to UTF-8".to_string(),
+            detail: e.to_string(),
+        }
     })?;
 
     // Decide whether to run HTML main-content conversion based on content type and extract_main flag
@@ -258,18 +227,13 @@ pub async fn fetch_url_content(url: &url::Url, extract_main: bool) -> Result<Ext
             )
         };
 
-        let text = html2text::from_read(source_html.as_bytes(), 120).map_err(|e| {
-            let payload = build_error_payload(
-                "ERR_FETCH_UNKNOWN",
-                "Failed to convert HTML content to text",
-                serde_json::json!({
-                    "url": url.as_str(),
-                    "hint": "The page structure could not be converted into text content.",
-                    "error": e.to_string()
-                }),
-            );
+        let text = html2text::from_read(source_html.as_bytes(), settings().text_width).map_err(|e| {
             warn!(target: "readability_extract", url = %url, "html2text conversion failed: {}", e);
-            anyhow!(payload)
+            FetchError::Decode {
+                url: url.to_string(),
+                message: "Failed to convert HTML content to text".to_string(),
+                detail: e.to_string(),
+            }
         })?;
 
         info!(target: "readability_extract", url = %url, elapsed_ms = started.elapsed().as_millis() as u64, len = text.len(), main_fragment_used = main_fragment_used, "html2text conversion succeeded");
@@ -357,7 +321,33 @@ static NEGATIVE_CLASS_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"(?i)hidden|^hid$| hid$| hid |^hid |banner|breadcrumb|combx|comment|com-|contact|foot|footer|footnote|masthead|media|meta|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|shopping|tags|tool|widget|subscribe|nav|author|byline").expect("valid negative regex")
 });
 
-const MIN_DYNAMIC_TEXT_CHARS: usize = 180;
+/// Tunables of the text extraction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExtractionSettings {
+    /// Line width of text converted from HTML
+    pub text_width: usize,
+    /// Least text an element matched by class or id needs to be taken as the main content
+    pub min_dynamic_text_chars: usize,
+}
+
+impl Default for ExtractionSettings {
+    fn default() -> Self {
+        Self {
+            text_width: 120,
+            min_dynamic_text_chars: 180,
+        }
+    }
+}
+
+static SETTINGS: Lazy<RwLock<ExtractionSettings>> = Lazy::new(Default::default);
+
+pub fn configure(settings: ExtractionSettings) {
+    *SETTINGS.write().unwrap() = settings;
+}
+
+fn settings() -> ExtractionSettings {
+    *SETTINGS.read().unwrap()
+}
 
 #[derive(Debug)]
 enum SelectedHtml {
@@ -482,6 +472,7 @@ fn extract_main_content_fragment(html: &str) -> Option<SelectedHtml> {
 
 fn select_by_positive_regex(document: &Html) -> Option<String> {
     let selector = Selector::parse("[class],[id]").ok()?;
+    let min_text_chars = settings().min_dynamic_text_chars;
     let mut best: Option<(String, usize)> = None;
 
     for element in document.select(&selector) {
@@ -510,7 +501,7 @@ fn select_by_positive_regex(document: &Html) -> Option<String> {
             let text = element.text().collect::<String>();
             let trimmed = text.trim();
             let text_len = trimmed.chars().count();
-            if text_len < MIN_DYNAMIC_TEXT_CHARS {
+            if text_len < min_text_chars {
                 continue;
             }
 
@@ -0,0 +1,106 @@
+// Masks secrets in log output. The configured API keys are registered here; their values and
+// the credentials in `Authorization`-style headers are replaced in every formatted event, on
+// stderr as well as in log notifications sent to MCP clients.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::sync::RwLock;
+use tracing_subscriber::fmt::MakeWriter;
+
+const MASK: &str = "[REDACTED]";
+
+// Shorter values would mask ordinary words all over the log
+const MIN_SECRET_LEN: usize = 6;
+
+static SECRETS: Lazy<RwLock<Vec<String>>> = Lazy::new(Default::default);
+
+// Header name, separator (`: `, `=`, `": "` in Debug output), optional auth scheme, credential
+static CREDENTIAL_HEADER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)\b((?:proxy-)?authorization|x-goog-api-key|x-api-key)("?\s*[:=]\s*"?)((?:bearer|basic|token)\s+)?[^\s",}]+"#,
+    )
+    .unwrap()
+});
+
+/// Replaces the secret values to mask, e.g. after the configuration was reloaded.
+pub fn set_secrets<I>(secrets: I)
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut secrets: Vec<String> = secrets
+        .into_iter()
+        .map(|secret| secret.trim().to_string())
+        .filter(|secret| secret.len() >= MIN_SECRET_LEN)
+        .collect();
+    // A secret containing another one must be replaced first
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    secrets.dedup();
+    *SECRETS.write().unwrap() = secrets;
+}
+
+/// `text` with registered secrets and header credentials masked.
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let mut text = match CREDENTIAL_HEADER.replace_all(text, format!("${{1}}${{2}}${{3}}{}", MASK))
+    {
+        Cow::Borrowed(_) => Cow::Borrowed(text),
+        Cow::Owned(replaced) => Cow::Owned(replaced),
+    };
+    for secret in SECRETS.read().unwrap().iter() {
+        if text.contains(secret.as_str()) {
+            text = Cow::Owned(text.replace(secret.as_str(), MASK));
+        }
+    }
+    text
+}
+
+/// `MakeWriter` for the fmt layer that redacts each formatted event before writing it.
+pub struct Redacting<M>(pub M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+pub struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    // The fmt layer writes each event with a single call, so secrets are never split
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_secrets_and_authorization_headers() {
+        set_secrets(["  goog-secret-123\n".to_string(), "abc".to_string()]);
+
+        assert_eq!(
+            redact("GET https://example.com/v1?key=goog-secret-123&q=abc"),
+            "GET https://example.com/v1?key=[REDACTED]&q=abc"
+        );
+        assert_eq!(
+            redact(r#"{"authorization": "Bearer jina_xyz", "accept": "*/*"}"#),
+            r#"{"authorization": "Bearer [REDACTED]", "accept": "*/*"}"#
+        );
+        assert_eq!(
+            redact("Proxy-Authorization: Basic dXNlcjpwdw=="),
+            "Proxy-Authorization: Basic [REDACTED]"
+        );
+        assert!(matches!(redact("nothing to hide"), Cow::Borrowed(_)));
+    }
+}
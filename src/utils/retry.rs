@@ -0,0 +1,293 @@
+// Shared retry policy for outbound requests. Transient failures (connection errors, 408,
+// 429, 5xx from gateways) are retried with exponential backoff and jitter, or after the
+// server's `Retry-After`, and then wait for a rate limiter slot like any other request so
+// a retry never comes sooner than the backend's politeness interval. Only idempotent
+// requests (GET, HEAD, ...) are retried; a POST is sent once.
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use reqwest::{header, Method, RequestBuilder, Response, StatusCode};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+use crate::utils::address_guard;
+use crate::utils::http::Backend;
+use crate::utils::progress::report_message;
+use crate::utils::rate_limit;
+
+/// Longest `Retry-After` waited for; a longer one is returned to the caller as is
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one; 1 disables retries
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubled for each further one
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn default_for(backend: Backend) -> Self {
+        let max_attempts = match backend {
+            // Retrying a scrape that was answered with a block page only makes it worse
+            Backend::DuckDuckGo | Backend::Felo => 2,
+            Backend::Google | Backend::Jina | Backend::Fetch => 3,
+        };
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+        }
+    }
+
+    // Exponential backoff with "equal jitter": half fixed, half random
+    fn backoff(&self, retry: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(1u32 << retry.saturating_sub(1).min(16))
+            .min(self.max_delay);
+        let half = exp / 2;
+        half + rand::thread_rng().gen_range(Duration::ZERO..=half)
+    }
+}
+
+impl FromStr for RetryPolicy {
+    type Err = anyhow::Error;
+
+    /// `ATTEMPTS[:BASE_SECS[:MAX_SECS]]`, e.g. `1` (no retries), `4` or `5:0.25:4`.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.trim().split(':');
+        let max_attempts = parts
+            .next()
+            .and_then(|p| p.trim().parse::<u32>().ok())
+            .filter(|n| *n >= 1)
+            .ok_or_else(|| anyhow!("Invalid attempt count in '{}' (expected 1 or more)", s))?;
+        let mut next_secs = |name: &str, default: Duration| match parts.next() {
+            None => Ok(default),
+            Some(p) => p
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                .map(Duration::from_secs_f64)
+                .ok_or_else(|| anyhow!("Invalid {} in '{}'", name, s)),
+        };
+        let base_delay = next_secs("base delay", Duration::from_millis(500))?;
+        let max_delay = next_secs("max delay", Duration::from_secs(8))?.max(base_delay);
+        if parts.next().is_some() {
+            return Err(anyhow!(
+                "Expected ATTEMPTS[:BASE_SECS[:MAX_SECS]], got '{}'",
+                s
+            ));
+        }
+        Ok(RetryPolicy {
+            max_attempts,
+            base_delay,
+            max_delay,
+        })
+    }
+}
+
+static OVERRIDES: Lazy<RwLock<HashMap<Backend, RetryPolicy>>> = Lazy::new(Default::default);
+
+/// Replaces the per-backend policies; backends not listed use `RetryPolicy::default_for`.
+pub fn configure(policies: HashMap<Backend, RetryPolicy>) {
+    *OVERRIDES.write().unwrap() = policies;
+}
+
+pub fn policy_for(backend: Backend) -> RetryPolicy {
+    OVERRIDES
+        .read()
+        .unwrap()
+        .get(&backend)
+        .copied()
+        .unwrap_or_else(|| RetryPolicy::default_for(backend))
+}
+
+/// Outbound request counts of one tool call.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryStats {
+    pub requests: u32,
+    pub attempts: u32,
+}
+
+tokio::task_local! {
+    static STATS: Cell<RetryStats>;
+}
+
+/// Runs `fut` and returns its output with the number of requests and attempts it made.
+pub async fn track<F: Future>(fut: F) -> (F::Output, RetryStats) {
+    STATS
+        .scope(Cell::new(RetryStats::default()), async move {
+            let output = fut.await;
+            (output, STATS.with(Cell::get))
+        })
+        .await
+}
+
+fn record(first_attempt: bool) {
+    let _ = STATS.try_with(|stats| {
+        let mut current = stats.get();
+        current.attempts += 1;
+        if first_attempt {
+            current.requests += 1;
+        }
+        stats.set(current);
+    });
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE
+    )
+}
+
+fn retryable_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504)
+}
+
+fn retryable_error(err: &reqwest::Error) -> bool {
+    if address_guard::find_blocked(err).is_some() || err.is_redirect() || err.is_builder() {
+        return false;
+    }
+    err.is_connect() || err.is_timeout() || err.is_request()
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(header::RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(secs) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value.trim()).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Sends `request`, retrying transient failures according to the policy of `backend`.
+/// The caller takes the rate limiter slot for the first attempt; each retry takes its own.
+/// Returns the last response or error once attempts are used up.
+pub async fn send(backend: Backend, request: RequestBuilder) -> reqwest::Result<Response> {
+    let policy = policy_for(backend);
+    let (client, request) = request.build_split();
+    let request = request?;
+
+    let mut attempt = 1;
+    loop {
+        // Streaming bodies cannot be replayed
+        let this_try = match request.try_clone() {
+            Some(this_try) if is_idempotent(request.method()) => this_try,
+            _ => {
+                record(true);
+                return client.execute(request).await;
+            }
+        };
+        record(attempt == 1);
+        let outcome = client.execute(this_try).await;
+        if attempt >= policy.max_attempts {
+            return outcome;
+        }
+
+        let (delay, reason) = match &outcome {
+            Ok(response) if retryable_status(response.status()) => (
+                retry_after(response).unwrap_or_else(|| policy.backoff(attempt)),
+                format!("HTTP {}", response.status().as_u16()),
+            ),
+            Err(err) if retryable_error(err) => (policy.backoff(attempt), err.to_string()),
+            _ => return outcome,
+        };
+        if delay > MAX_RETRY_AFTER {
+            debug!(
+                "Not retrying {}: server asked to wait {:?}",
+                request.url(),
+                delay
+            );
+            return outcome;
+        }
+
+        warn!(
+            "Request to {} failed ({}); retrying in {:.1}s (attempt {} of {})",
+            request.url(),
+            reason,
+            delay.as_secs_f64(),
+            attempt + 1,
+            policy.max_attempts
+        );
+        report_message(format!(
+            "Retrying after {} in {:.1}s (attempt {} of {})",
+            reason,
+            delay.as_secs_f64(),
+            attempt + 1,
+            policy.max_attempts
+        ));
+        tokio::time::sleep(delay).await;
+        if let Err(e) = rate_limit::acquire_for(backend, request.url()).await {
+            debug!("Not retrying {}: {}", request.url(), e);
+            return outcome;
+        }
+        drop(outcome);
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{self, Reply};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // Answers the first request with 503 and `Retry-After: 0`, later ones with 200
+    async fn flaky_server(hits: Arc<AtomicUsize>) -> String {
+        test_server::serve(move |_| {
+            if hits.fetch_add(1, Ordering::SeqCst) == 0 {
+                Reply::new("503 Service Unavailable", &[("Retry-After", "0")], "")
+            } else {
+                Reply::new("200 OK", &[], "ok")
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn retries_transient_status_and_counts_attempts() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let url = flaky_server(hits.clone()).await;
+        let client = reqwest::Client::new();
+
+        let (response, stats) = track(send(Backend::Fetch, client.get(&url))).await;
+        assert_eq!(response.unwrap().text().await.unwrap(), "ok");
+        assert_eq!((stats.requests, stats.attempts), (1, 2));
+
+        // The retry waits for a rate limiter slot of the host like a first attempt would
+        hits.store(0, Ordering::SeqCst);
+        let url = url.replace("127.0.0.1", "localhost");
+        let limit = rate_limit::limit_for(Backend::Fetch);
+        for _ in 0..limit.burst {
+            rate_limit::acquire_host("localhost").await.unwrap();
+        }
+        let started = tokio::time::Instant::now();
+        let (response, _) = track(send(Backend::Fetch, client.get(&url))).await;
+        assert_eq!(response.unwrap().text().await.unwrap(), "ok");
+        assert!(started.elapsed() >= limit.min_interval / 2);
+
+        // POST is sent once, even on a 503
+        hits.store(0, Ordering::SeqCst);
+        let (response, stats) = track(send(Backend::Fetch, client.post(&url).body("q"))).await;
+        assert_eq!(response.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!((stats.requests, stats.attempts), (1, 1));
+
+        let policy: RetryPolicy = "5:0.25:4".parse().unwrap();
+        assert_eq!(policy.max_attempts, 5);
+        assert!("0".parse::<RetryPolicy>().is_err());
+    }
+}
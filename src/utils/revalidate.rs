@@ -0,0 +1,182 @@
+// Conditional revalidation of cached pages. Extraction results are cached together with the
+// `ETag` and `Last-Modified` of the response they came from. Once the entry expires, the page
+// is requested with `If-None-Match` / `If-Modified-Since`; a 304 answer renews the cached
+// result without downloading or extracting the page again.
+
+use reqwest::{header, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use tracing::info;
+
+use crate::utils::cache::{self, Lookup};
+use crate::utils::http::Backend;
+
+/// Validators of the response a cached result was extracted from.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn from_response(response: &Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Validators {
+            etag: header(header::ETAG),
+            last_modified: header(header::LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Makes `request` conditional on the page still matching these validators.
+    pub fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+
+    // A 304 may carry updated validators; missing ones stay as they were
+    fn updated_by(self, newer: Validators) -> Self {
+        Validators {
+            etag: newer.etag.or(self.etag),
+            last_modified: newer.last_modified.or(self.last_modified),
+        }
+    }
+}
+
+/// Outcome of a page download that may have been conditional.
+pub enum Download<T> {
+    Modified(T, Validators),
+    /// 304 answer to a conditional request; only returned when validators were sent
+    NotModified(Validators),
+}
+
+impl<T> Download<T> {
+    /// `NotModified` if `response` answers a conditional request with 304. Call after the
+    /// redirect checks, before the status check.
+    pub fn not_modified(sent: Option<&Validators>, response: &Response) -> Option<Self> {
+        (sent.is_some() && response.status() == StatusCode::NOT_MODIFIED)
+            .then(|| Download::NotModified(Validators::from_response(response)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Validated<T> {
+    validators: Validators,
+    value: T,
+}
+
+/// Serves `key` from the page cache. A fresh entry is returned as is. Otherwise `download` is
+/// called with the validators of the expired entry, if it has any, so it can send a
+/// conditional request; its result is cached.
+pub async fn cached<T, E, F, Fut>(key: &str, download: F) -> Result<T, E>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce(Option<Validators>) -> Fut,
+    Fut: Future<Output = Result<Download<T>, E>>,
+{
+    let stale = match cache::lookup::<Validated<T>>(Backend::Fetch, key).await {
+        Some(Lookup::Fresh(entry)) => return Ok(entry.value),
+        Some(Lookup::Stale(entry)) if !entry.validators.is_empty() => Some(entry),
+        _ => None,
+    };
+
+    let entry = match download(stale.as_ref().map(|entry| entry.validators.clone())).await? {
+        Download::Modified(value, validators) => Validated { validators, value },
+        Download::NotModified(validators) => {
+            let entry = stale.expect("304 is only accepted for conditional requests");
+            info!("{} not modified; renewing cached result", key);
+            Validated {
+                validators: entry.validators.updated_by(validators),
+                value: entry.value,
+            }
+        }
+    };
+    if entry.validators.is_empty() {
+        cache::put(Backend::Fetch, key, &entry).await;
+    } else {
+        cache::put_revalidatable(Backend::Fetch, key, &entry).await;
+    }
+    Ok(entry.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::cache::CacheConfig;
+    use crate::utils::test_server::{self, Reply};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // Serves a page with an ETag and answers requests carrying that ETag with 304
+    async fn etag_server(full_responses: Arc<AtomicUsize>) -> String {
+        test_server::serve(move |request| {
+            if request
+                .to_ascii_lowercase()
+                .contains("if-none-match: \"v1\"")
+            {
+                Reply::Raw(
+                    "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n"
+                        .to_string(),
+                )
+            } else {
+                full_responses.fetch_add(1, Ordering::SeqCst);
+                Reply::new("200 OK", &[("ETag", "\"v1\"")], "page")
+            }
+        })
+        .await
+    }
+
+    async fn download(
+        url: &str,
+        validators: Option<Validators>,
+    ) -> reqwest::Result<Download<String>> {
+        let mut request = reqwest::Client::new().get(url);
+        if let Some(validators) = &validators {
+            request = validators.apply(request);
+        }
+        let response = request.send().await?;
+        if let Some(not_modified) = Download::not_modified(validators.as_ref(), &response) {
+            return Ok(not_modified);
+        }
+        let validators = Validators::from_response(&response);
+        Ok(Download::Modified(response.text().await?, validators))
+    }
+
+    #[tokio::test]
+    async fn expired_entry_is_renewed_by_304() {
+        let full_responses = Arc::new(AtomicUsize::new(0));
+        let url = etag_server(full_responses.clone()).await;
+        // Entries expire right away but stay around for revalidation
+        let mut config = CacheConfig::default();
+        config.ttls.insert(Backend::Fetch, Duration::from_millis(1));
+
+        cache::with_private_cache(config, async {
+            let key = format!("test:{}", url);
+            for _ in 0..3 {
+                let page = cached(&key, |validators| download(&url, validators))
+                    .await
+                    .unwrap();
+                assert_eq!(page, "page");
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await;
+        assert_eq!(full_responses.load(Ordering::SeqCst), 1);
+    }
+}
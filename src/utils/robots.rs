@@ -0,0 +1,368 @@
+// Optional robots.txt compliance (RFC 9309) for page fetches. When enabled, `/robots.txt`
+// of each origin is downloaded once, cached with a TTL, and evaluated for the user agent
+// the page request is sent with; `Crawl-delay` spaces out requests to the same origin.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::utils::body_limit;
+use crate::utils::fetch_error::FetchError;
+use crate::utils::http::{self, Backend};
+use crate::utils::retry;
+use crate::utils::wildcard;
+
+/// How long parsed rules are reused (RFC 9309 caps caching at 24 hours)
+const ROBOTS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Unreachable robots.txt means "disallow everything"; retried sooner than a real file
+const UNREACHABLE_TTL: Duration = Duration::from_secs(60);
+/// RFC 9309 requires parsing at least the first 500 KiB
+const MAX_ROBOTS_BYTES: u64 = 512 * 1024;
+/// Longest `Crawl-delay` honored; larger values are clamped so a tool call cannot hang
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(30);
+const MAX_CACHED_ORIGINS: usize = 1000;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns robots.txt compliance on or off for all fetch tools.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<f64>,
+}
+
+/// Parsed robots.txt.
+#[derive(Debug, Default)]
+pub struct RobotsTxt {
+    groups: Vec<Group>,
+    /// Set when the file could not be retrieved (5xx, network error): everything is disallowed
+    unreachable: bool,
+}
+
+impl RobotsTxt {
+    pub fn parse(body: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        // A user-agent line after rules starts a new group; consecutive ones share a group
+        let mut in_agents = false;
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if !in_agents {
+                        groups.push(Group::default());
+                        in_agents = true;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                "allow" | "disallow" => {
+                    in_agents = false;
+                    // An empty Disallow allows everything, which is the default anyway
+                    if let (Some(group), false) = (groups.last_mut(), value.is_empty()) {
+                        group.rules.push(Rule {
+                            allow: key.trim().eq_ignore_ascii_case("allow"),
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_agents = false;
+                    if let (Some(group), Ok(delay)) = (groups.last_mut(), value.parse::<f64>()) {
+                        if delay.is_finite() && delay >= 0.0 {
+                            group.crawl_delay = Some(delay);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        RobotsTxt {
+            groups,
+            unreachable: false,
+        }
+    }
+
+    fn unreachable() -> Self {
+        RobotsTxt {
+            groups: Vec::new(),
+            unreachable: true,
+        }
+    }
+
+    // Groups naming the most specific token contained in `user_agent`, else the `*` groups
+    fn groups_for(&self, user_agent: &str) -> Vec<&Group> {
+        let user_agent = user_agent.to_ascii_lowercase();
+        let best = self
+            .groups
+            .iter()
+            .flat_map(|g| g.agents.iter())
+            .filter(|agent| agent.as_str() != "*" && user_agent.contains(agent.as_str()))
+            .max_by_key(|agent| agent.len());
+        let wanted = best.map(String::as_str).unwrap_or("*");
+        self.groups
+            .iter()
+            .filter(|g| g.agents.iter().any(|agent| agent == wanted))
+            .collect()
+    }
+
+    /// The rule blocking `path_and_query` for `user_agent`, or `None` if it may be fetched.
+    /// The longest matching pattern decides; on a tie, allow wins.
+    pub fn disallowing_rule(&self, user_agent: &str, path_and_query: &str) -> Option<String> {
+        if self.unreachable {
+            return Some("robots.txt could not be retrieved".to_string());
+        }
+        if path_and_query == "/robots.txt" {
+            return None;
+        }
+        let decisive = self
+            .groups_for(user_agent)
+            .into_iter()
+            .flat_map(|g| g.rules.iter())
+            .filter(|rule| pattern_matches(&rule.pattern, path_and_query))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))?;
+        (!decisive.allow).then(|| format!("Disallow: {}", decisive.pattern))
+    }
+
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<Duration> {
+        self.groups_for(user_agent)
+            .into_iter()
+            .filter_map(|g| g.crawl_delay)
+            .reduce(f64::max)
+            .map(|secs| Duration::from_secs_f64(secs).min(MAX_CRAWL_DELAY))
+    }
+}
+
+// `*` matches any run of characters, a trailing `$` anchors the pattern at the end
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    match pattern.strip_suffix('$') {
+        Some(pattern) => wildcard::matches(pattern.as_bytes(), path.as_bytes()),
+        None => wildcard::matches_prefix(pattern.as_bytes(), path.as_bytes()),
+    }
+}
+
+struct CachedRobots {
+    robots: Arc<RobotsTxt>,
+    expires: Instant,
+    /// Earliest time the next request to this origin may start (Crawl-delay)
+    next_request: Instant,
+}
+
+static CACHE: Lazy<Mutex<HashMap<String, CachedRobots>>> = Lazy::new(Default::default);
+
+async fn robots_for(origin: &str) -> Arc<RobotsTxt> {
+    if let Some(cached) = CACHE.lock().unwrap().get(origin) {
+        if cached.expires > Instant::now() {
+            return cached.robots.clone();
+        }
+    }
+
+    let (robots, ttl) = fetch_robots(origin).await;
+    let robots = Arc::new(robots);
+    let mut cache = CACHE.lock().unwrap();
+    if cache.len() >= MAX_CACHED_ORIGINS {
+        let now = Instant::now();
+        cache.retain(|_, cached| cached.expires > now);
+        if cache.len() >= MAX_CACHED_ORIGINS {
+            cache.clear();
+        }
+    }
+    let next_request = cache
+        .get(origin)
+        .map(|cached| cached.next_request)
+        .unwrap_or_else(Instant::now);
+    cache.insert(
+        origin.to_string(),
+        CachedRobots {
+            robots: robots.clone(),
+            expires: Instant::now() + ttl,
+            next_request,
+        },
+    );
+    robots
+}
+
+async fn fetch_robots(origin: &str) -> (RobotsTxt, Duration) {
+    let robots_url = format!("{}/robots.txt", origin);
+    debug!("Fetching {}", robots_url);
+    let response = match retry::send(
+        Backend::Fetch,
+        http::client_for(Backend::Fetch).get(&robots_url),
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("robots.txt unreachable at {}: {}", robots_url, e);
+            return (RobotsTxt::unreachable(), UNREACHABLE_TTL);
+        }
+    };
+    let status = response.status();
+    if status.is_server_error() {
+        warn!("robots.txt unreachable at {}: HTTP {}", robots_url, status);
+        return (RobotsTxt::unreachable(), UNREACHABLE_TTL);
+    }
+    if !status.is_success() {
+        // 4xx: no robots.txt, everything is allowed
+        return (RobotsTxt::default(), ROBOTS_TTL);
+    }
+    match body_limit::read_truncated(response, MAX_ROBOTS_BYTES).await {
+        Ok(body) => (
+            RobotsTxt::parse(&String::from_utf8_lossy(&body)),
+            ROBOTS_TTL,
+        ),
+        Err(e) => {
+            warn!("robots.txt unreachable at {}: {}", robots_url, e);
+            (RobotsTxt::unreachable(), UNREACHABLE_TTL)
+        }
+    }
+}
+
+// Reserves the next request slot for `origin` and waits for it
+async fn wait_for_slot(origin: &str, delay: Duration) {
+    let start = {
+        let mut cache = CACHE.lock().unwrap();
+        let Some(cached) = cache.get_mut(origin) else {
+            return;
+        };
+        let start = cached.next_request.max(Instant::now());
+        cached.next_request = start + delay;
+        start
+    };
+    let wait = start.saturating_duration_since(Instant::now());
+    if !wait.is_zero() {
+        info!("Waiting {:?} for Crawl-delay of {}", wait, origin);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+fn path_and_query(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+/// With compliance enabled, refuses `url` if robots.txt disallows it for `user_agent`.
+/// A no-op when disabled.
+pub async fn check(url: &Url, user_agent: &str) -> Result<(), FetchError> {
+    if !enabled() || !matches!(url.scheme(), "http" | "https") {
+        return Ok(());
+    }
+    let robots = robots_for(&url.origin().ascii_serialization()).await;
+    match robots.disallowing_rule(user_agent, &path_and_query(url)) {
+        Some(rule) => Err(FetchError::RobotsDisallowed {
+            url: url.to_string(),
+            rule,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// With compliance enabled, waits out the `Crawl-delay` of the origin of `url`. Called
+/// right before the request is sent, so answers served from the cache are not delayed.
+pub async fn wait_for_crawl_delay(url: &Url, user_agent: &str) {
+    if !enabled() || !matches!(url.scheme(), "http" | "https") {
+        return;
+    }
+    let origin = url.origin().ascii_serialization();
+    if let Some(delay) = robots_for(&origin).await.crawl_delay(user_agent) {
+        wait_for_slot(&origin, delay).await;
+    }
+}
+
+/// Re-checks the page a request was redirected to. Crawl-delay is not applied again,
+/// the request has already been made.
+pub async fn check_redirected(
+    requested: &Url,
+    final_url: &Url,
+    user_agent: &str,
+) -> Result<(), FetchError> {
+    if !enabled() || requested == final_url {
+        return Ok(());
+    }
+    let robots = robots_for(&final_url.origin().ascii_serialization()).await;
+    match robots.disallowing_rule(user_agent, &path_and_query(final_url)) {
+        Some(rule) => Err(FetchError::RobotsDisallowed {
+            url: final_url.to_string(),
+            rule,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\
+User-agent: *
+Disallow: /private
+Allow: /private/public
+Disallow: /*.pdf$
+Crawl-delay: 2
+
+# Firefox-specific group
+User-agent: firefox
+User-agent: other
+Disallow: /
+Allow: /docs/
+";
+
+    #[test]
+    fn evaluates_most_specific_group_and_longest_rule() {
+        let robots = RobotsTxt::parse(ROBOTS);
+        let chrome = "Mozilla/5.0 (Windows NT 10.0) Chrome/120.0.0.0 Safari/537.36";
+        let firefox = "Mozilla/5.0 (X11; rv:115.0) Gecko/20100101 Firefox/115.0";
+
+        assert!(robots.disallowing_rule(chrome, "/private/x").is_some());
+        assert!(robots
+            .disallowing_rule(chrome, "/private/public/x")
+            .is_none());
+        assert!(robots.disallowing_rule(chrome, "/a/b.pdf").is_some());
+        assert!(robots.disallowing_rule(chrome, "/a/b.pdf?x=1").is_none());
+        assert_eq!(robots.crawl_delay(chrome), Some(Duration::from_secs(2)));
+
+        assert!(robots.disallowing_rule(firefox, "/index.html").is_some());
+        assert!(robots.disallowing_rule(firefox, "/docs/intro").is_none());
+        assert_eq!(robots.crawl_delay(firefox), None);
+        assert!(RobotsTxt::unreachable()
+            .disallowing_rule(chrome, "/")
+            .is_some());
+    }
+
+    #[test]
+    fn wildcard_heavy_rules_match_in_linear_time() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow: /*a*a*a*a*a*a*a*a*a*a*a*a*b\n");
+        let path = format!("/{}", "a".repeat(50_000));
+        let started = std::time::Instant::now();
+        assert!(robots.disallowing_rule("test", &path).is_none());
+        assert!(robots
+            .disallowing_rule("test", &format!("{}b", path))
+            .is_some());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -1,17 +1,14 @@
 use anyhow::{anyhow, Result};
 use futures::StreamExt;
-use once_cell::sync::Lazy;
 use rand::seq::SliceRandom;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Mutex;
-use std::time::{Duration, Instant};
 use tracing::{debug, warn};
 use uuid::Uuid;
 
-// Constants
-const CACHE_DURATION: Duration = Duration::from_secs(5 * 60); // 5 minutes
+use crate::utils::cache;
+use crate::utils::http::{self, Backend};
+use crate::utils::rate_limit;
+use crate::utils::retry;
 
 // Rotating User Agents
 static USER_AGENTS: &[&str] = &[
@@ -22,16 +19,6 @@ static USER_AGENTS: &[&str] = &[
     "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
 ];
 
-// Cache for Felo results
-static FELO_CACHE: Lazy<Mutex<HashMap<String, CacheEntry>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
-
-#[derive(Debug, Clone)]
-struct CacheEntry {
-    result: String,
-    timestamp: Instant,
-}
-
 #[derive(Debug, Serialize)]
 struct FeloSearchPayload {
     query: String,
@@ -60,14 +47,6 @@ struct FeloDataContent {
     text: Option<String>,
 }
 
-// HTTP client
-static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
-    Client::builder()
-        .timeout(Duration::from_secs(30))
-        .build()
-        .expect("Failed to create HTTP client")
-});
-
 /// Get a random user agent from the list
 fn get_random_user_agent() -> &'static str {
     let mut rng = rand::thread_rng();
@@ -79,26 +58,12 @@ fn get_cache_key(query: &str) -> String {
     format!("felo-{}", query)
 }
 
-/// Clear old entries from the cache
-fn clear_old_cache() {
-    let mut cache = FELO_CACHE.lock().unwrap();
-    let now = Instant::now();
-    cache.retain(|_, entry| now.duration_since(entry.timestamp) < CACHE_DURATION);
-}
-
 /// Search using the Felo AI API
 pub async fn search_felo(prompt: &str, stream: bool) -> Result<String> {
-    // Clear old cache entries
-    clear_old_cache();
-
     // Check cache first if not streaming
     if !stream {
-        let cache_key = get_cache_key(prompt);
-        let cache = FELO_CACHE.lock().unwrap();
-        if let Some(cached_result) = cache.get(&cache_key) {
-            if Instant::now().duration_since(cached_result.timestamp) < CACHE_DURATION {
-                return Ok(cached_result.result.clone());
-            }
+        if let Some(cached) = cache::get::<String>(Backend::Felo, &get_cache_key(prompt)).await {
+            return Ok(cached);
         }
     }
 
@@ -121,7 +86,8 @@ pub async fn search_felo(prompt: &str, stream: bool) -> Result<String> {
     debug!("Sending Felo AI request with payload: {:?}", payload);
 
     // Make the request
-    let response = HTTP_CLIENT
+    rate_limit::acquire(Backend::Felo).await?;
+    let request = http::client_for(Backend::Felo)
         .post("https://api.felo.ai/search/threads")
         .header("accept", "*/*")
         .header("accept-encoding", "gzip, deflate, br")
@@ -138,9 +104,8 @@ pub async fn search_felo(prompt: &str, stream: bool) -> Result<String> {
         .header("sec-fetch-mode", "cors")
         .header("sec-fetch-site", "same-site")
         .header("user-agent", user_agent)
-        .json(&payload)
-        .send()
-        .await?;
+        .json(&payload);
+    let response = retry::send(Backend::Felo, request).await?;
 
     if !response.status().is_success() {
         return Err(anyhow!(
@@ -177,7 +142,7 @@ pub async fn search_felo(prompt: &str, stream: bool) -> Result<String> {
                                     if stream {
                                         // For streaming, we would yield the delta here
                                         // But since we're returning a single string, we'll just continue
-                                        debug!("Received delta: {}", delta);
+                                        debug!("Received delta of {} bytes", delta.len());
                                     }
                                 }
                             }
@@ -194,15 +159,7 @@ pub async fn search_felo(prompt: &str, stream: bool) -> Result<String> {
 
     // Cache the complete response if not streaming
     if !stream && !full_response.is_empty() {
-        let cache_key = get_cache_key(prompt);
-        let mut cache = FELO_CACHE.lock().unwrap();
-        cache.insert(
-            cache_key,
-            CacheEntry {
-                result: full_response.clone(),
-                timestamp: Instant::now(),
-            },
-        );
+        cache::put(Backend::Felo, &get_cache_key(prompt), &full_response).await;
     }
 
     if full_response.is_empty() {
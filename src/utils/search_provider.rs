@@ -0,0 +1,312 @@
+// Common interface of the web search engines. Each engine is an adapter implementing
+// `SearchProvider`: it turns a `SearchRequest` into one request to the engine and the answer
+// into `SearchResult`s. Caching, the domain policy and result formatting are done here, the
+// same way for every engine.
+
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::mcp::types::ContentItem;
+use crate::utils::cache;
+use crate::utils::domain_policy;
+use crate::utils::http::Backend;
+
+/// Period the results are restricted to, e.g. `d7` for the past week. A missing count means 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeRange {
+    pub unit: TimeUnit,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeUnit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl TimeUnit {
+    pub fn letter(self) -> char {
+        match self {
+            TimeUnit::Day => 'd',
+            TimeUnit::Week => 'w',
+            TimeUnit::Month => 'm',
+            TimeUnit::Year => 'y',
+        }
+    }
+}
+
+impl FromStr for TimeRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        let invalid = || {
+            anyhow!(
+                "Invalid time range '{}' (expected d, w, m or y followed by a count, e.g. m6)",
+                s
+            )
+        };
+        let mut chars = s.chars();
+        let unit = match chars.next() {
+            Some('d') => TimeUnit::Day,
+            Some('w') => TimeUnit::Week,
+            Some('m') => TimeUnit::Month,
+            Some('y') => TimeUnit::Year,
+            _ => return Err(invalid()),
+        };
+        let count = match chars.as_str() {
+            "" => 1,
+            digits => digits.parse().map_err(|_| invalid())?,
+        };
+        if count == 0 {
+            return Err(invalid());
+        }
+        Ok(TimeRange { unit, count })
+    }
+}
+
+impl fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.unit.letter(), self.count)
+    }
+}
+
+/// One page of a web search, in terms every engine understands.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchRequest {
+    pub query: String,
+    /// 1-based
+    pub page: u32,
+    /// Results per page
+    pub count: u32,
+    /// ISO 639-1 code, e.g. `en`
+    pub language: Option<String>,
+    /// ISO 3166-1 alpha-2 code, e.g. `us`
+    pub region: Option<String>,
+    pub time_range: Option<TimeRange>,
+    /// Domain the results must come from
+    pub site: Option<String>,
+}
+
+impl SearchRequest {
+    pub fn new(query: impl Into<String>, page: u32, count: u32) -> Self {
+        SearchRequest {
+            query: query.into(),
+            page,
+            count,
+            language: None,
+            region: None,
+            time_range: None,
+            site: None,
+        }
+    }
+
+    /// The query with the site restriction added in the `site:` syntax all engines accept.
+    pub fn full_query(&self) -> String {
+        match &self.site {
+            Some(site) => format!("{} site:{}", self.query, site),
+            None => self.query.clone(),
+        }
+    }
+
+    /// 0-based index of the first result on the requested page
+    pub fn offset(&self) -> u32 {
+        self.page.saturating_sub(1).saturating_mul(self.count)
+    }
+}
+
+/// A search result, whichever engine it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+    pub display_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_published: Option<String>,
+}
+
+/// What an engine returned for one request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchPage {
+    pub results: Vec<SearchResult>,
+    /// Estimated number of matches, if the engine reports it
+    pub total_results: Option<u64>,
+}
+
+/// A search engine adapter.
+pub trait SearchProvider: Send + Sync {
+    /// Backend whose cache, rate limit and retry policy apply to the engine
+    fn backend(&self) -> Backend;
+
+    /// Key under which the results for `request` are cached. Overridden by engines whose
+    /// results depend on more than the request, or on less.
+    fn cache_key(&self, request: &SearchRequest) -> String {
+        serde_json::to_string(request).expect("search requests serialize")
+    }
+
+    /// Fetches the results for `request` from the engine. May return more than
+    /// `request.count` results; the surplus is dropped.
+    fn fetch<'a>(&'a self, request: &'a SearchRequest) -> BoxFuture<'a, Result<SearchPage>>;
+}
+
+/// Results of `search` that the domain policy let through.
+pub struct SearchOutcome {
+    pub page: SearchPage,
+    /// Results removed by the domain policy
+    pub filtered: usize,
+}
+
+/// Runs `request` on `provider`, serving it from the cache when possible, and applies the
+/// domain policy to the results.
+pub async fn search(
+    provider: &dyn SearchProvider,
+    request: &SearchRequest,
+) -> Result<SearchOutcome> {
+    let backend = provider.backend();
+    let key = provider.cache_key(request);
+    let mut page = match cache::get::<SearchPage>(backend, &key).await {
+        Some(page) => page,
+        None => {
+            let page = provider.fetch(request).await?;
+            cache::put(backend, &key, &page).await;
+            page
+        }
+    };
+    page.results.truncate(request.count as usize);
+    info!("Found {} results", page.results.len());
+
+    let (results, filtered) = domain_policy::filter_results(page.results, |r| r.url.as_str());
+    if filtered > 0 {
+        info!("Domain policy removed {} results", filtered);
+    }
+    page.results = results;
+    Ok(SearchOutcome { page, filtered })
+}
+
+/// Numbered Markdown list of the results, one link and snippet each.
+pub fn format_results(results: &[SearchResult]) -> String {
+    results
+        .iter()
+        .enumerate()
+        .map(|(index, result)| {
+            format!(
+                "{}. [{}]({})\n   {}",
+                index + 1,
+                result.title,
+                result.url,
+                result.snippet
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Note on results hidden by the domain policy; empty if there were none.
+pub fn filtered_note(filtered: usize) -> String {
+    if filtered == 0 {
+        String::new()
+    } else {
+        format!("\n\n({} results hidden by the domain policy)", filtered)
+    }
+}
+
+/// `resource_link` content items pointing at the results.
+pub fn resource_links(results: &[SearchResult]) -> impl Iterator<Item = ContentItem> + '_ {
+    results
+        .iter()
+        .map(|r| ContentItem::resource_link(&r.url, &r.title, Some(r.snippet.clone())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::cache::CacheConfig;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Fixed;
+
+    impl SearchProvider for Fixed {
+        fn backend(&self) -> Backend {
+            Backend::DuckDuckGo
+        }
+
+        fn fetch<'a>(&'a self, request: &'a SearchRequest) -> BoxFuture<'a, Result<SearchPage>> {
+            let results = (0..5)
+                .map(|i| SearchResult {
+                    title: format!("{} {}", request.full_query(), i),
+                    url: format!("https://example.com/{}", i),
+                    snippet: String::new(),
+                    display_url: "example.com".to_string(),
+                    favicon: None,
+                    date_published: None,
+                })
+                .collect();
+            Box::pin(async move {
+                Ok(SearchPage {
+                    results,
+                    total_results: Some(5),
+                })
+            })
+        }
+    }
+
+    // Counts the requests that reach the engine
+    #[derive(Default)]
+    struct Counting(AtomicUsize);
+
+    impl SearchProvider for Counting {
+        fn backend(&self) -> Backend {
+            Backend::DuckDuckGo
+        }
+
+        fn fetch<'a>(&'a self, _: &'a SearchRequest) -> BoxFuture<'a, Result<SearchPage>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async { Ok(SearchPage::default()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn no_cache_skips_the_cached_page() {
+        let provider = Counting::default();
+        let request = SearchRequest::new("no cache test", 1, 3);
+        cache::with_private_cache(CacheConfig::default(), async {
+            search(&provider, &request).await.unwrap();
+            search(&provider, &request).await.unwrap();
+            assert_eq!(provider.0.load(Ordering::SeqCst), 1);
+
+            // What the `noCache` argument of the search tools sets
+            cache::with_no_cache(true, search(&provider, &request))
+                .await
+                .unwrap();
+            assert_eq!(provider.0.load(Ordering::SeqCst), 2);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn truncates_results_to_count() {
+        let mut request = SearchRequest::new("provider test", 2, 3);
+        request.site = Some("example.com".to_string());
+        request.time_range = Some("m6".parse().unwrap());
+
+        let outcome = search(&Fixed, &request).await.unwrap();
+        assert_eq!(outcome.page.results.len(), 3);
+        assert_eq!(
+            outcome.page.results[0].title,
+            "provider test site:example.com 0"
+        );
+        assert_eq!(request.offset(), 3);
+        assert_eq!(request.time_range.unwrap().to_string(), "m6");
+        assert_eq!("W".parse::<TimeRange>().unwrap().count, 1);
+        assert!("x1".parse::<TimeRange>().is_err());
+    }
+}
@@ -0,0 +1,64 @@
+// Minimal HTTP/1.1 server for tests. Each connection carries one request: its head is handed
+// to a handler, whose reply is written back before the connection is closed.
+
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// What the server writes back for a request.
+pub enum Reply {
+    /// Written as is
+    Raw(String),
+    /// `head`, then `chunk` over and over until the client hangs up
+    Endless { head: String, chunk: Vec<u8> },
+}
+
+impl Reply {
+    /// Response with the status line `status` (e.g. "200 OK"), `headers`, `body` and the
+    /// `Content-Length` and `Connection: close` headers.
+    pub fn new(status: &str, headers: &[(&str, &str)], body: &str) -> Self {
+        let mut head = format!("HTTP/1.1 {}\r\n", status);
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        Reply::Raw(format!(
+            "{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            head,
+            body.len(),
+            body
+        ))
+    }
+}
+
+/// Serves `handle` on a free local port. It gets the request line and headers of every
+/// request. Returns the server's origin, e.g. `http://127.0.0.1:41234`.
+pub async fn serve<F>(handle: F) -> String
+where
+    F: Fn(&str) -> Reply + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = Arc::new(handle);
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let handle = Arc::clone(&handle);
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                match handle(&request) {
+                    Reply::Raw(response) => {
+                        let _ = socket.write_all(response.as_bytes()).await;
+                    }
+                    Reply::Endless { head, chunk } => {
+                        if socket.write_all(head.as_bytes()).await.is_ok() {
+                            while socket.write_all(&chunk).await.is_ok() {}
+                        }
+                    }
+                }
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
@@ -0,0 +1,63 @@
+// `*` wildcard matching for robots.txt rules and domain patterns. Greedy with a single
+// backtrack point: on a mismatch, the last `*` takes one more character. O(n·m) at worst,
+// whatever the pattern, since robots.txt patterns come from remote sites.
+
+/// Whether `pattern` matches the whole of `text`; `*` matches any run of bytes.
+pub fn matches(pattern: &[u8], text: &[u8]) -> bool {
+    match_with(pattern, text, false)
+}
+
+/// Whether `pattern` matches the start of `text`.
+pub fn matches_prefix(pattern: &[u8], text: &[u8]) -> bool {
+    match_with(pattern, text, true)
+}
+
+fn match_with(pattern: &[u8], text: &[u8], prefix: bool) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` seen and the text position it was last resumed from
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if prefix && p == pattern.len() {
+            return true;
+        }
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                star = Some((p, t));
+            }
+            Some(&c) if c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((after_star, from)) => {
+                    p = after_star;
+                    t = from + 1;
+                    star = Some((after_star, t));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_whole_text_or_prefix() {
+        assert!(matches(b"*.example.com", b"www.example.com"));
+        assert!(matches(b"docs.*.org", b"docs.python.org"));
+        assert!(!matches(b"*.example.com", b"example.com"));
+        assert!(matches(b"a*b*c", b"aXbYbZc"));
+        assert!(!matches(b"a*b*c", b"aXbYbZ"));
+        assert!(matches(b"**", b""));
+
+        assert!(matches_prefix(b"/private", b"/private/x"));
+        assert!(matches_prefix(b"/*.pdf", b"/a/b.pdf?x=1"));
+        assert!(!matches_prefix(b"/*.pdf", b"/a/b.html"));
+        assert!(matches_prefix(b"", b"/anything"));
+    }
+}